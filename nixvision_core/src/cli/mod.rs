@@ -0,0 +1,432 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::{Mutex, PoisonError};
+
+use opencv::{core::Point2f, prelude::*};
+
+use nixvision_lib::filters::scale::{Reference, Scale};
+use crate::commands::CommandManager;
+use crate::commands::calibration::Calibration;
+use crate::config::recipes::{Recipe, RecipeStore};
+use crate::config::settings::AppConfig;
+use crate::io::frames::FrameCapture;
+use crate::pipelines::parallel::run_batch;
+use crate::pipelines::processing::{DynamicPipeline, PipelineOptions};
+use crate::pipelines::profiler::Profiler;
+use crate::pipelines::step::PipelineStep;
+use crate::pipelines::sweep::{Sweep, SweepSpec};
+use crate::pipelines::validation::ImageFormat;
+use crate::ui::Interface;
+
+const USAGE: &str = "\
+Uso: nixvision [SUBCOMANDO] [OPCIONES]
+
+Sin subcomando se abre el menú interactivo.
+
+Subcomandos:
+  process   --recipe <archivo|nombre> --input <imagen> [--output <imagen>]
+            [--overlay original|processed|side_by_side] [--debug-dir <carpeta>] [--montage] [--profile]
+            [--camera <nombre> | --mm-per-pixel <mm>]
+  capture   --camera <nombre> [--fps 10] [--seconds 2] [--recipe <archivo|nombre>] [--output <carpeta>]
+            [--overlay original|processed|side_by_side] [--threads N] [--profile] [--profile-csv <archivo>]
+  autotune  --input <imagen> [--sweep <barrido.toml>] [--prefix <nombre_base>] [--debug-dir <carpeta>] [--montage]
+            [--top 5] [--expected-area <px²>] [--threads N] [--camera <nombre> | --mm-per-pixel <mm>]
+            [--profile] [--profile-csv <archivo>]
+  calibrate --camera <nombre> con uno de:
+              --mm-per-pixel <mm>
+              --points x1,y1,x2,y2 --distance <mm>
+              --recipe <archivo|nombre> --size <mm> [--reference length|square|circle] [--input <imagen>]
+              (sin --input se captura un frame de la cámara)
+            --clear borra la calibración
+  calibrate-lens --camera <nombre> --images <carpeta> --square <mm> [--pattern 9x6]
+            (esquinas interiores del tablero; --clear borra la calibración de lente)
+  help      Muestra esta ayuda
+
+Con --camera (o --mm-per-pixel) las medidas se dan también en mm; el paso `undistort`
+usa la calibración de lente de --camera.
+
+Códigos de salida: 0 = OK, 1 = error de ejecución, 2 = uso incorrecto.";
+
+/// Error de la interfaz de línea de comandos; cada variante tiene su código de salida.
+#[derive(Debug)]
+pub enum CliError {
+    /// Argumentos inválidos o faltantes (código 2).
+    Usage(String),
+    /// Fallo al ejecutar el comando (código 1).
+    Runtime(String),
+}
+
+impl CliError {
+    fn exit_code(&self) -> ExitCode {
+        match self {
+            CliError::Usage(_) => ExitCode::from(2),
+            CliError::Runtime(_) => ExitCode::from(1),
+        }
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Usage(msg) | CliError::Runtime(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl From<opencv::Error> for CliError {
+    fn from(e: opencv::Error) -> Self {
+        CliError::Runtime(format!("OpenCV: {}", e))
+    }
+}
+
+/// Opciones `--clave valor` de un subcomando; `--clave` sin valor equivale a `--clave true`.
+struct Options {
+    values: HashMap<String, String>,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Self, CliError> {
+        let mut values = HashMap::new();
+        let mut iter = args.iter().peekable();
+
+        while let Some(arg) = iter.next() {
+            let Some(key) = arg.strip_prefix("--") else {
+                return Err(CliError::Usage(format!("Argumento inesperado: '{}'", arg)));
+            };
+            let value = match iter.peek() {
+                Some(next) if !next.starts_with("--") => iter.next().cloned().unwrap_or_default(),
+                _ => "true".to_string(),
+            };
+            values.insert(key.to_string(), value);
+        }
+        Ok(Self { values })
+    }
+
+    fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    fn flag(&self, key: &str) -> bool {
+        matches!(self.get(key), Some("true") | Some("1") | Some("yes"))
+    }
+
+    fn required(&self, key: &str) -> Result<&str, CliError> {
+        self.get(key).ok_or_else(|| CliError::Usage(format!("Falta la opción obligatoria --{}", key)))
+    }
+
+    fn parsed<T: std::str::FromStr>(&self, key: &str, default: T) -> Result<T, CliError> {
+        match self.get(key) {
+            Some(raw) => raw
+                .parse()
+                .map_err(|_| CliError::Usage(format!("Valor inválido para --{}: '{}'", key, raw))),
+            None => Ok(default),
+        }
+    }
+}
+
+pub struct Cli;
+
+impl Cli {
+    /// Ejecuta un subcomando sin interacción y devuelve el código de salida del proceso.
+    pub fn run(args: &[String]) -> ExitCode {
+        let (command, rest) = match args.split_first() {
+            Some((command, rest)) => (command.as_str(), rest),
+            None => ("help", &[][..]),
+        };
+
+        let result = Options::parse(rest).and_then(|opts| match command {
+            "process" => Self::process(&opts),
+            "capture" => Self::capture(&opts),
+            "autotune" => Self::autotune(&opts),
+            "calibrate" => Self::calibrate(&opts),
+            "calibrate-lens" => Self::calibrate_lens(&opts),
+            "help" | "--help" | "-h" => {
+                println!("{}", USAGE);
+                Ok(())
+            }
+            other => Err(CliError::Usage(format!("Subcomando desconocido: '{}'", other))),
+        });
+
+        match result {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                Interface::error(&e.to_string());
+                if let CliError::Usage(_) = e {
+                    eprintln!("\n{}", USAGE);
+                }
+                e.exit_code()
+            }
+        }
+    }
+
+    fn process(opts: &Options) -> Result<(), CliError> {
+        let recipe = Self::load_recipe(opts.required("recipe")?)?;
+        let input = opts.required("input")?;
+        let output = opts.get("output").unwrap_or("resultado_procesado.jpg");
+
+        let options = PipelineOptions {
+            debug_dir: opts.get("debug-dir").map(PathBuf::from),
+            montage: opts.flag("montage"),
+            ..Self::pipeline_options(opts, &AppConfig::load())?
+        };
+
+        let img = Self::load_input(input)?;
+        let steps = Self::validate(&recipe.steps, ImageFormat::of(&img))?;
+        let res = DynamicPipeline::run_with(&img, &steps, &options)?;
+        CommandManager::print_result(&res);
+        if opts.flag("profile") {
+            let mut profiler = Profiler::new();
+            profiler.record(&res);
+            CommandManager::print_profile(&profiler, None);
+        }
+
+        if !FrameCapture::save_image(&res.image, output)? {
+            return Err(CliError::Runtime(format!("No se pudo escribir '{}'", output)));
+        }
+        Interface::success(&format!("Imagen guardada correctamente como: {}", output));
+        Ok(())
+    }
+
+    fn capture(opts: &Options) -> Result<(), CliError> {
+        let camera_name = opts.required("camera")?;
+        let fps: f64 = opts.parsed("fps", 10.0)?;
+        let seconds: u64 = opts.parsed("seconds", 2)?;
+        if fps <= 0.0 {
+            return Err(CliError::Usage("--fps debe ser mayor que 0".to_string()));
+        }
+
+        let config = AppConfig::load();
+        let cam = config
+            .cameras
+            .iter()
+            .find(|c| c.name == camera_name)
+            .ok_or_else(|| CliError::Runtime(format!("La cámara '{}' no está configurada", camera_name)))?;
+
+        let steps = match opts.get("recipe") {
+            Some(recipe) => Self::validate(&Self::load_recipe(recipe)?.steps, ImageFormat::bgr8())?,
+            None => Vec::new(),
+        };
+
+        let default_folder = format!("Capturas/{}", cam.name.replace(" ", "_"));
+        let folder = opts.get("output").unwrap_or(&default_folder);
+        if Path::new(folder).exists() {
+            return Err(CliError::Runtime(format!("La ruta '{}' ya existe", folder)));
+        }
+        std::fs::create_dir_all(folder)
+            .map_err(|e| CliError::Runtime(format!("No se pudo crear '{}': {}", folder, e)))?;
+
+        Interface::info(&format!("🚀 Iniciando captura en {}...", cam.name));
+        let frames = FrameCapture::capture_sequence(&cam.url, fps, seconds)?;
+        if frames.is_empty() {
+            return Err(CliError::Runtime(format!("No se recibieron frames de '{}'", cam.url)));
+        }
+
+        let profiler = Mutex::new(Profiler::new());
+        let options = Self::pipeline_options(opts, &config)?;
+        let counts = run_batch(&frames, &steps, &options, Self::threads(opts, &config)?, |i, procesada| {
+            profiler.lock().unwrap_or_else(PoisonError::into_inner).record(procesada);
+            let filename = format!("{}/frame_{:03}.jpg", folder, i);
+            FrameCapture::write_image(&procesada.image, &filename)?;
+            Ok(procesada.count())
+        })?;
+        let profiler = profiler.into_inner().unwrap_or_else(PoisonError::into_inner);
+
+        Interface::success(&format!("{} frames guardados en: {}", frames.len(), folder));
+        if steps.iter().any(|s| matches!(s, PipelineStep::Contours { .. })) {
+            Interface::object_counts(&counts);
+        }
+        Self::report_profile(opts, &profiler);
+        Ok(())
+    }
+
+    fn autotune(opts: &Options) -> Result<(), CliError> {
+        let input = opts.required("input")?;
+        let img = Self::load_input(input)?;
+        let prefix = CommandManager::output_prefix(input);
+        let base_name = opts.get("prefix").unwrap_or(&prefix);
+
+        // Sin `--sweep` se usa la búsqueda clásica Blur/Canny/Área
+        let mut spec = match opts.get("sweep") {
+            Some(path) => SweepSpec::load(Path::new(path))
+                .map_err(|e| CliError::Runtime(format!("No se pudo cargar el barrido '{}': {}", path, e)))?,
+            None => SweepSpec::autotune_default(),
+        };
+        // Atajos para no editar el archivo de barrido
+        spec.scoring.top_n = opts.parsed("top", spec.scoring.top_n)?;
+        if opts.get("expected-area").is_some() {
+            spec.scoring.expected_area = Some(opts.parsed("expected-area", 0.0)?);
+        }
+        let sweep = Sweep::new(spec).map_err(|e| CliError::Runtime(format!("Barrido inválido: {}", e)))?;
+        if !CommandManager::validar_barrido(&sweep, ImageFormat::of(&img)) {
+            return Err(CliError::Runtime("Barrido inválido".to_string()));
+        }
+
+        let config = AppConfig::load();
+        let options = PipelineOptions {
+            debug_dir: opts.get("debug-dir").map(PathBuf::from),
+            montage: opts.flag("montage"),
+            ..Self::pipeline_options(opts, &config)?
+        };
+        let threads = Self::threads(opts, &config)?;
+        let report = CommandManager::run_autotune(&img, &sweep, base_name, &options, threads)?;
+        Self::report_profile(opts, &report.profiler);
+        if report.ranking.is_empty() {
+            return Err(CliError::Runtime(format!("Ninguna de las {} combinaciones detectó objetos", report.results.len())));
+        }
+        if let Some(path) = &report.best_recipe {
+            Interface::info(&format!("Para aplicarla: nixvision process --recipe {} --input {}", path.display(), input));
+        }
+        Ok(())
+    }
+
+    fn calibrate(opts: &Options) -> Result<(), CliError> {
+        let camera = opts.required("camera")?;
+        let config = AppConfig::load();
+        let cam = config
+            .camera(camera)
+            .ok_or_else(|| CliError::Runtime(format!("La cámara '{}' no está configurada", camera)))?;
+
+        if opts.flag("clear") {
+            Calibration::save(camera, None).map_err(CliError::Runtime)?;
+            Interface::success(&format!("Calibración de '{}' eliminada.", camera));
+            return Ok(());
+        }
+
+        let scale = if opts.get("mm-per-pixel").is_some() {
+            Scale::new(opts.parsed("mm-per-pixel", 0.0)?).map_err(|e| CliError::Usage(e.to_string()))?
+        } else if let Some(raw) = opts.get("points") {
+            let coords: Vec<f32> = raw.split(',').map(|v| v.trim().parse().ok()).collect::<Option<_>>().unwrap_or_default();
+            let [x1, y1, x2, y2] = coords[..] else {
+                return Err(CliError::Usage(format!("--points espera x1,y1,x2,y2, se recibió '{}'", raw)));
+            };
+            let (a, b) = (Point2f::new(x1, y1), Point2f::new(x2, y2));
+            let distance = opts.parsed("distance", 0.0)?;
+            Scale::from_points(a, b, distance).map_err(|e| CliError::Usage(e.to_string()))?
+        } else {
+            let recipe = Self::load_recipe(opts.get("recipe").ok_or_else(|| {
+                CliError::Usage("Indica --mm-per-pixel, --points o --recipe con la referencia".to_string())
+            })?)?;
+            let raw = opts.get("reference").unwrap_or("length");
+            let reference = Reference::ALL
+                .into_iter()
+                .find(|r| r.name() == raw)
+                .ok_or_else(|| CliError::Usage(format!("Referencia desconocida: '{}'", raw)))?;
+            let size: f64 = opts.parsed("size", 0.0)?;
+
+            let img = match opts.get("input") {
+                Some(input) => Self::load_input(input)?,
+                None => {
+                    let frame = FrameCapture::capture_from_stream(&cam.url)?;
+                    if frame.empty() {
+                        return Err(CliError::Runtime(format!("No se recibió ningún frame de '{}'", cam.url)));
+                    }
+                    frame
+                }
+            };
+            let steps = Self::validate(&recipe.steps, ImageFormat::of(&img))?;
+            Calibration::from_reference(&img, &steps, reference, size, cam.intrinsics.as_ref())?.map_err(CliError::Runtime)?
+        };
+
+        Calibration::save(camera, Some(scale)).map_err(CliError::Runtime)?;
+        Interface::success(&format!(
+            "Escala de '{}' guardada: {:.5} mm/px ({:.2} px/mm)",
+            camera, scale.mm_per_pixel, 1.0 / scale.mm_per_pixel
+        ));
+        Ok(())
+    }
+
+    fn calibrate_lens(opts: &Options) -> Result<(), CliError> {
+        let camera = opts.required("camera")?;
+        if AppConfig::load().camera(camera).is_none() {
+            return Err(CliError::Runtime(format!("La cámara '{}' no está configurada", camera)));
+        }
+
+        if opts.flag("clear") {
+            Calibration::save_lens(camera, None).map_err(CliError::Runtime)?;
+            Interface::success(&format!("Calibración de lente de '{}' eliminada.", camera));
+            return Ok(());
+        }
+
+        let images = opts.required("images")?;
+        let raw = opts.get("pattern").unwrap_or("9x6");
+        let pattern = Calibration::parse_pattern(raw)
+            .ok_or_else(|| CliError::Usage(format!("--pattern espera columnas x filas (p. ej. 9x6), se recibió '{}'", raw)))?;
+        let square: f64 = opts.parsed("square", 0.0)?;
+
+        let report = Calibration::calibrate_lens(Path::new(images), pattern, square).map_err(CliError::Runtime)?;
+        Calibration::save_lens(camera, Some(report.intrinsics.clone())).map_err(CliError::Runtime)?;
+        Interface::success(&format!(
+            "Lente de '{}' guardada: error RMS {:.3} px con {} capturas.",
+            camera,
+            report.intrinsics.rms,
+            report.used.len()
+        ));
+        Ok(())
+    }
+
+    /// `--threads N` tiene prioridad sobre `threads` de la configuración (0 = todos los núcleos).
+    fn threads(opts: &Options, config: &AppConfig) -> Result<usize, CliError> {
+        opts.parsed("threads", config.threads)
+    }
+
+    /// Estilo de anotación de la configuración; `--overlay` elige la imagen de fondo,
+    /// `--camera`/`--mm-per-pixel` la escala y `--camera` la calibración de lente.
+    fn pipeline_options(opts: &Options, config: &AppConfig) -> Result<PipelineOptions, CliError> {
+        let mut options = PipelineOptions::from_config(config);
+        options.overlay = opts.parsed("overlay", options.overlay)?;
+        options.scale = Self::scale(opts, config)?;
+        options.intrinsics = opts.get("camera").and_then(|name| config.camera(name)).and_then(|cam| cam.intrinsics.clone());
+        Ok(options)
+    }
+
+    /// `--mm-per-pixel` tiene prioridad sobre la calibración de `--camera`.
+    fn scale(opts: &Options, config: &AppConfig) -> Result<Option<Scale>, CliError> {
+        if opts.get("mm-per-pixel").is_some() {
+            let mm = opts.parsed("mm-per-pixel", 0.0)?;
+            return Scale::new(mm).map(Some).map_err(|e| CliError::Usage(e.to_string()));
+        }
+        match opts.get("camera") {
+            Some(name) => {
+                let cam = config
+                    .camera(name)
+                    .ok_or_else(|| CliError::Runtime(format!("La cámara '{}' no está configurada", name)))?;
+                if cam.scale().is_none() {
+                    Interface::warning(&format!("La cámara '{}' no está calibrada: medidas solo en píxeles.", name));
+                }
+                Ok(cam.scale())
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// `--profile` imprime el resumen de tiempos; `--profile-csv` además lo exporta.
+    fn report_profile(opts: &Options, profiler: &Profiler) {
+        let csv = opts.get("profile-csv").map(Path::new);
+        if opts.flag("profile") || csv.is_some() {
+            CommandManager::print_profile(profiler, csv);
+        }
+    }
+
+    fn validate(steps: &[PipelineStep], input: ImageFormat) -> Result<Vec<PipelineStep>, CliError> {
+        CommandManager::validar_receta(steps, input)
+            .ok_or_else(|| CliError::Runtime("Receta inválida".to_string()))
+    }
+
+    /// Acepta la ruta a un archivo de receta o el nombre de una receta guardada.
+    fn load_recipe(arg: &str) -> Result<Recipe, CliError> {
+        let path = Path::new(arg);
+        let result = if path.exists() { Recipe::load(path) } else { RecipeStore::load(arg) };
+        result.map_err(|e| CliError::Runtime(format!("No se pudo cargar la receta '{}': {}", arg, e)))
+    }
+
+    fn load_input(path: &str) -> Result<Mat, CliError> {
+        let img = FrameCapture::load_image(path)?;
+        // imread no falla con archivos inexistentes: devuelve una Mat vacía.
+        if img.empty() {
+            return Err(CliError::Runtime(format!("No se pudo cargar la imagen '{}'", path)));
+        }
+        Ok(img)
+    }
+}
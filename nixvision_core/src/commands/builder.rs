@@ -0,0 +1,616 @@
+use inquire::{Confirm, Select};
+use opencv::{core::Mat, highgui};
+use std::fmt;
+use nixvision_lib::filters::contour::{ContourFilter, ContourMode, Selection};
+use nixvision_lib::filters::morphology::{KernelShape, MorphOp};
+use crate::ui::Interface;
+use crate::commands::CommandManager;
+use crate::io::frames::FrameCapture;
+use crate::config::settings::AppConfig;
+use crate::pipelines::processing::{DynamicPipeline, PipelineOptions};
+use crate::pipelines::step::PipelineStep;
+use crate::pipelines::validation::ImageFormat;
+
+/// Opción concreta del menú "Añadir paso al pipeline".
+/// `default_step` hace un `match` exhaustivo: una opción nueva sin operación asociada
+/// no compila; los tests comprueban además que cada opción está en `CATEGORIES` y que
+/// su paso por defecto modifica de verdad una imagen de prueba.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MenuOption {
+    Grayscale,
+    Hsv,
+    ValueChannel,
+    Normalize,
+    BackgroundCorrection,
+    Clahe,
+    GaussianBlur,
+    MedianBlur,
+    Bilateral,
+    Sharpen,
+    Laplacian,
+    Canny,
+    Threshold,
+    Otsu,
+    Triangle,
+    AdaptiveMean,
+    AdaptiveGaussian,
+    Morphology,
+    Translate,
+    Rotate,
+    Undistort,
+    Contours,
+}
+
+/// Categoría del menú con sus opciones.
+pub struct MenuCategory {
+    pub title: &'static str,
+    pub prompt: &'static str,
+    pub options: &'static [MenuOption],
+}
+
+pub const CATEGORIES: &[MenuCategory] = &[
+    MenuCategory {
+        title: "1. Color",
+        prompt: "Conversión:",
+        options: &[MenuOption::Grayscale, MenuOption::Hsv, MenuOption::ValueChannel],
+    },
+    MenuCategory {
+        title: "2. Iluminación",
+        prompt: "Método:",
+        options: &[MenuOption::Normalize, MenuOption::BackgroundCorrection, MenuOption::Clahe],
+    },
+    MenuCategory {
+        title: "3. Ruido",
+        prompt: "Filtro:",
+        options: &[MenuOption::GaussianBlur, MenuOption::MedianBlur, MenuOption::Bilateral],
+    },
+    MenuCategory {
+        title: "4. Bordes/Canny",
+        prompt: "Algoritmo:",
+        options: &[MenuOption::Sharpen, MenuOption::Laplacian, MenuOption::Canny],
+    },
+    MenuCategory {
+        title: "5. Umbralización",
+        prompt: "Método:",
+        options: &[
+            MenuOption::Threshold,
+            MenuOption::Otsu,
+            MenuOption::Triangle,
+            MenuOption::AdaptiveMean,
+            MenuOption::AdaptiveGaussian,
+        ],
+    },
+    MenuCategory {
+        title: "6. Morfología",
+        prompt: "Operación:",
+        options: &[MenuOption::Morphology],
+    },
+    MenuCategory {
+        title: "7. Geometría (Afín)",
+        prompt: "Transformación:",
+        options: &[MenuOption::Undistort, MenuOption::Translate, MenuOption::Rotate],
+    },
+    MenuCategory {
+        title: "8. Contornos (Métricas)",
+        prompt: "Extracción:",
+        options: &[MenuOption::Contours],
+    },
+];
+
+const FINISH: &str = "🚀 Finalizar y Procesar";
+
+impl MenuOption {
+    pub fn label(self) -> &'static str {
+        match self {
+            MenuOption::Grayscale => "Grises",
+            MenuOption::Hsv => "HSV",
+            MenuOption::ValueChannel => "Canal V (HSV)",
+            MenuOption::Normalize => "Normalizar",
+            MenuOption::BackgroundCorrection => "Background Correction",
+            MenuOption::Clahe => "CLAHE",
+            MenuOption::GaussianBlur => "Gaussian Blur",
+            MenuOption::MedianBlur => "Median Blur",
+            MenuOption::Bilateral => "Bilateral Filter",
+            MenuOption::Sharpen => "Sharpen",
+            MenuOption::Laplacian => "Laplacian",
+            MenuOption::Canny => "Canny (Bordes)",
+            MenuOption::Threshold => "Umbral fijo",
+            MenuOption::Otsu => "Otsu (automático)",
+            MenuOption::Triangle => "Triángulo (automático)",
+            MenuOption::AdaptiveMean => "Adaptativo (media)",
+            MenuOption::AdaptiveGaussian => "Adaptativo (gaussiano)",
+            MenuOption::Morphology => "Erosión / Dilatación / Apertura / Cierre...",
+            MenuOption::Translate => "Traslación",
+            MenuOption::Rotate => "Rotación",
+            MenuOption::Undistort => "Corrección de lente (calibración de la cámara)",
+            MenuOption::Contours => "Contornos y métricas",
+        }
+    }
+
+    /// Opción de menú que produce `step` (para editar un paso existente).
+    pub fn of(step: &PipelineStep) -> Self {
+        match step {
+            PipelineStep::Grayscale => MenuOption::Grayscale,
+            PipelineStep::Hsv => MenuOption::Hsv,
+            PipelineStep::ValueChannel => MenuOption::ValueChannel,
+            PipelineStep::Normalize => MenuOption::Normalize,
+            PipelineStep::BackgroundCorrection => MenuOption::BackgroundCorrection,
+            PipelineStep::Clahe { .. } => MenuOption::Clahe,
+            PipelineStep::GaussianBlur { .. } => MenuOption::GaussianBlur,
+            PipelineStep::MedianBlur { .. } => MenuOption::MedianBlur,
+            PipelineStep::Bilateral { .. } => MenuOption::Bilateral,
+            PipelineStep::Sharpen => MenuOption::Sharpen,
+            PipelineStep::Laplacian => MenuOption::Laplacian,
+            PipelineStep::Canny { .. } => MenuOption::Canny,
+            PipelineStep::Threshold { .. } => MenuOption::Threshold,
+            PipelineStep::Otsu { .. } => MenuOption::Otsu,
+            PipelineStep::Triangle { .. } => MenuOption::Triangle,
+            PipelineStep::AdaptiveMean { .. } => MenuOption::AdaptiveMean,
+            PipelineStep::AdaptiveGaussian { .. } => MenuOption::AdaptiveGaussian,
+            PipelineStep::Morphology { .. } => MenuOption::Morphology,
+            PipelineStep::Translate { .. } => MenuOption::Translate,
+            PipelineStep::Rotate { .. } => MenuOption::Rotate,
+            PipelineStep::Undistort => MenuOption::Undistort,
+            PipelineStep::Contours { .. } => MenuOption::Contours,
+        }
+    }
+
+    /// Paso de la opción con sus parámetros por defecto, sin preguntar nada.
+    pub fn default_step(self) -> PipelineStep {
+        match self {
+            MenuOption::Grayscale => PipelineStep::Grayscale,
+            MenuOption::Hsv => PipelineStep::Hsv,
+            MenuOption::ValueChannel => PipelineStep::ValueChannel,
+            MenuOption::Normalize => PipelineStep::Normalize,
+            MenuOption::BackgroundCorrection => PipelineStep::BackgroundCorrection,
+            MenuOption::Clahe => PipelineStep::Clahe { clip_limit: 2.0, grid: 8 },
+            MenuOption::GaussianBlur => PipelineStep::GaussianBlur { kernel: 5 },
+            MenuOption::MedianBlur => PipelineStep::MedianBlur { kernel: 5 },
+            MenuOption::Bilateral => PipelineStep::Bilateral { diameter: 9, sigma_color: 75.0, sigma_space: 75.0 },
+            MenuOption::Sharpen => PipelineStep::Sharpen,
+            MenuOption::Laplacian => PipelineStep::Laplacian,
+            MenuOption::Canny => PipelineStep::Canny { low: 50.0, high: 150.0 },
+            MenuOption::Threshold => PipelineStep::Threshold { value: 127.0, invert: false },
+            MenuOption::Otsu => PipelineStep::Otsu { invert: false },
+            MenuOption::Triangle => PipelineStep::Triangle { invert: false },
+            MenuOption::AdaptiveMean => PipelineStep::AdaptiveMean { block_size: 11, c: 2.0, invert: false },
+            MenuOption::AdaptiveGaussian => PipelineStep::AdaptiveGaussian { block_size: 11, c: 2.0, invert: false },
+            MenuOption::Morphology => PipelineStep::Morphology {
+                operation: MorphOp::Close,
+                shape: KernelShape::Rect,
+                size: 5,
+                iterations: 1,
+            },
+            MenuOption::Translate => PipelineStep::Translate { x: 50.0, y: 50.0 },
+            MenuOption::Rotate => PipelineStep::Rotate { angle: 45.0 },
+            MenuOption::Undistort => PipelineStep::Undistort,
+            MenuOption::Contours => PipelineStep::Contours {
+                filter: ContourFilter::min_area(500.0),
+                mode: ContourMode::External,
+                level: None,
+                select: Selection::Largest,
+            },
+        }
+    }
+
+    /// Pide los parámetros de la opción y construye el paso correspondiente.
+    /// Si se edita un paso existente (`current`), sus valores se ofrecen por defecto;
+    /// si no, los de `default_step`.
+    pub fn build(self, current: Option<&PipelineStep>) -> PipelineStep {
+        let step = current
+            .filter(|step| MenuOption::of(step) == self)
+            .cloned()
+            .unwrap_or_else(|| self.default_step());
+
+        match step {
+            PipelineStep::Clahe { clip_limit, grid } => {
+                let clip_limit = ask("Clip Limit para CLAHE:", clip_limit);
+                let grid = ask("Tamaño de la rejilla CLAHE:", grid);
+                PipelineStep::Clahe { clip_limit, grid }
+            }
+            PipelineStep::GaussianBlur { kernel } => {
+                // Preguntamos el tamaño del kernel (Punto 2 y 31 de la práctica) [cite: 19, 31]
+                PipelineStep::GaussianBlur { kernel: ask("Tamaño del Blur (impar):", kernel) }
+            }
+            PipelineStep::MedianBlur { kernel } => PipelineStep::MedianBlur { kernel: ask("Tamaño del Blur (impar):", kernel) },
+            PipelineStep::Bilateral { diameter, sigma_color, sigma_space } => {
+                let diameter = ask("Diámetro del vecindario:", diameter);
+                let sigma_color = ask("Sigma Color:", sigma_color);
+                let sigma_space = ask("Sigma Space:", sigma_space);
+                PipelineStep::Bilateral { diameter, sigma_color, sigma_space }
+            }
+            PipelineStep::Canny { low, high } => {
+                // Parámetros dinámicos para el reporte (Punto 20 y 31)
+                let low = ask("Canny Low Threshold:", low);
+                let high = ask("Canny High Threshold:", high);
+                PipelineStep::Canny { low, high }
+            }
+            PipelineStep::Threshold { value, invert } => {
+                let value = ask("Umbral (0-255):", value);
+                PipelineStep::Threshold { value, invert: ask_invert(invert) }
+            }
+            PipelineStep::Otsu { invert } => PipelineStep::Otsu { invert: ask_invert(invert) },
+            PipelineStep::Triangle { invert } => PipelineStep::Triangle { invert: ask_invert(invert) },
+            PipelineStep::AdaptiveMean { block_size, c, invert } | PipelineStep::AdaptiveGaussian { block_size, c, invert } => {
+                let block_size = ask("Tamaño de la vecindad (impar):", block_size);
+                let c = ask("Constante C (se resta a la media):", c);
+                let invert = ask_invert(invert);
+                if self == MenuOption::AdaptiveMean {
+                    PipelineStep::AdaptiveMean { block_size, c, invert }
+                } else {
+                    PipelineStep::AdaptiveGaussian { block_size, c, invert }
+                }
+            }
+            PipelineStep::Morphology { operation, shape, size, iterations } => {
+                let operation = pick("Operación:", &MorphOp::ALL, operation, MorphOp::name);
+                let shape = pick("Forma del elemento estructurante:", &KernelShape::ALL, shape, KernelShape::name);
+                let size = ask("Tamaño del elemento (px):", size);
+                let iterations = ask("Iteraciones:", iterations);
+                PipelineStep::Morphology { operation, shape, size, iterations }
+            }
+            PipelineStep::Translate { x, y } => {
+                let x = ask("X:", x);
+                let y = ask("Y:", y);
+                PipelineStep::Translate { x, y }
+            }
+            PipelineStep::Rotate { angle } => PipelineStep::Rotate { angle: ask("Ángulo:", angle) },
+            PipelineStep::Contours { mut filter, mode: m, level: lv, select: sel } => {
+                // Filtro de área mínima para evitar ruido (Punto 26) [cite: 26]
+                filter.min_area = ask("Área mínima para filtrar:", filter.min_area);
+
+                let advanced = filter != ContourFilter::min_area(filter.min_area);
+                if confirm("¿Configurar filtros avanzados (forma, perímetro, borde)?", advanced) {
+                    filter.max_area = ask_optional("Área máxima (vacío = sin límite):", filter.max_area);
+                    filter.min_perimeter = ask_optional("Perímetro mínimo:", filter.min_perimeter);
+                    filter.max_perimeter = ask_optional("Perímetro máximo:", filter.max_perimeter);
+                    filter.min_circularity = ask_optional("Circularidad mínima (0-1):", filter.min_circularity);
+                    filter.max_circularity = ask_optional("Circularidad máxima (0-1):", filter.max_circularity);
+                    filter.min_aspect_ratio = ask_optional("Relación de aspecto mínima (ancho/alto):", filter.min_aspect_ratio);
+                    filter.max_aspect_ratio = ask_optional("Relación de aspecto máxima (ancho/alto):", filter.max_aspect_ratio);
+                    filter.min_solidity = ask_optional("Solidez mínima (0-1):", filter.min_solidity);
+                    filter.exclude_border = confirm("¿Descartar objetos que tocan el borde?", filter.exclude_border);
+                }
+
+                let (mode, level) = if confirm("¿Detectar agujeros (árbol de contornos)?", m == ContourMode::Tree) {
+                    // Vacío = todos los objetos exteriores (niveles pares)
+                    let raw = Interface::ask_text("Nivel del árbol a medir (vacío = objetos, 1 = agujeros):", &lv.map(|l| l.to_string()).unwrap_or_default());
+                    (ContourMode::Tree, raw.trim().parse().ok())
+                } else {
+                    (ContourMode::External, None)
+                };
+
+                let select = match pick("Objetos a conservar:", &SELECTIONS, selection_kind(sel), selection_label) {
+                    Selection::KLargest(_) => {
+                        let k = match sel { Selection::KLargest(k) => k, _ => 3 };
+                        Selection::KLargest(ask("¿Cuántos objetos (los de mayor área)?", k))
+                    }
+                    other => other,
+                };
+                Interface::info("Se ha añadido la extracción de Área y Perímetro.");
+                PipelineStep::Contours { filter, mode, level, select }
+            }
+            // Pasos sin parámetros
+            step => step,
+        }
+    }
+}
+
+impl fmt::Display for MenuOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.label())
+    }
+}
+
+/// Estrategias de selección que ofrece el menú (`k` se pregunta aparte).
+const SELECTIONS: [Selection; 4] = [Selection::Largest, Selection::KLargest(0), Selection::All, Selection::ClosestToCenter];
+
+fn selection_kind(selection: Selection) -> Selection {
+    match selection {
+        Selection::KLargest(_) => Selection::KLargest(0),
+        other => other,
+    }
+}
+
+fn selection_label(selection: Selection) -> &'static str {
+    match selection {
+        Selection::Largest => "El de mayor área",
+        Selection::KLargest(_) => "Los K de mayor área",
+        Selection::All => "Todos",
+        Selection::ClosestToCenter => "El más cercano al centro",
+    }
+}
+
+/// Elige un valor de una lista cerrada, empezando en `default`.
+fn pick<T: Copy + PartialEq>(prompt: &str, options: &[T], default: T, name: fn(T) -> &'static str) -> T {
+    let labels: Vec<&str> = options.iter().map(|o| name(*o)).collect();
+    let start = options.iter().position(|o| *o == default).unwrap_or(0);
+    Select::new(prompt, labels)
+        .with_starting_cursor(start)
+        .prompt()
+        .ok()
+        .and_then(|label| options.iter().copied().find(|o| name(*o) == label))
+        .unwrap_or(default)
+}
+
+fn confirm(prompt: &str, default: bool) -> bool {
+    Confirm::new(prompt).with_default(default).prompt().unwrap_or(default)
+}
+
+/// Umbral invertido: para objetos más oscuros que el fondo.
+fn ask_invert(default: bool) -> bool {
+    confirm("¿Invertir? (objeto oscuro sobre fondo claro)", default)
+}
+
+/// Límite opcional: vacío lo desactiva.
+fn ask_optional(prompt: &str, default: Option<f64>) -> Option<f64> {
+    loop {
+        let raw = Interface::ask_text(prompt, &default.map(|v| v.to_string()).unwrap_or_default());
+        if raw.trim().is_empty() {
+            return None;
+        }
+        match raw.trim().parse() {
+            Ok(value) => return Some(value),
+            Err(_) => Interface::error(&format!("'{}' no es un valor válido.", raw)),
+        }
+    }
+}
+
+/// Pregunta un valor numérico hasta que se pueda interpretar.
+fn ask<T: std::str::FromStr + fmt::Display>(prompt: &str, default: T) -> T {
+    loop {
+        let raw = Interface::ask_text(prompt, &default.to_string());
+        match raw.trim().parse() {
+            Ok(value) => return value,
+            Err(_) => Interface::error(&format!("'{}' no es un valor válido.", raw)),
+        }
+    }
+}
+
+const ADD: &str = "➕ Añadir paso";
+const EDIT: &str = "✏️ Editar paso";
+const REMOVE: &str = "🗑️ Eliminar paso";
+const MOVE: &str = "↕️ Mover paso";
+const PREVIEW: &str = "👁️ Previsualizar";
+
+pub struct PipelineBuilder;
+
+impl PipelineBuilder {
+    /// Construye una receta nueva desde el menú interactivo.
+    pub fn run(preview: Option<&Mat>) -> Vec<PipelineStep> {
+        Self::edit(Vec::new(), preview)
+    }
+
+    /// Editor de recetas: añade, edita, elimina y reordena pasos mostrando siempre
+    /// la receta actual. Con `preview` se puede ver el resultado antes de procesar.
+    pub fn edit(mut pipeline: Vec<PipelineStep>, preview: Option<&Mat>) -> Vec<PipelineStep> {
+        loop {
+            Self::print(&pipeline);
+
+            let mut acciones = vec![ADD];
+            if !pipeline.is_empty() {
+                acciones.extend([EDIT, REMOVE]);
+            }
+            if pipeline.len() > 1 {
+                acciones.push(MOVE);
+            }
+            if preview.is_some() && !pipeline.is_empty() {
+                acciones.push(PREVIEW);
+            }
+            acciones.push(FINISH);
+
+            match Select::new("Editor de pipeline:", acciones).prompt() {
+                Ok(ADD) => {
+                    if let Some(option) = Self::pick_option() {
+                        pipeline.push(Self::build_valid(option, None));
+                    }
+                }
+                Ok(EDIT) => {
+                    if let Some(i) = Self::pick_step(&pipeline, "Paso a editar:") {
+                        pipeline[i] = Self::build_valid(MenuOption::of(&pipeline[i]), Some(&pipeline[i]));
+                    }
+                }
+                Ok(REMOVE) => {
+                    if let Some(i) = Self::pick_step(&pipeline, "Paso a eliminar:") {
+                        let removed = pipeline.remove(i);
+                        Interface::info(&format!("Paso eliminado: {}", removed));
+                    }
+                }
+                Ok(MOVE) => {
+                    if let Some(from) = Self::pick_step(&pipeline, "Paso a mover:") {
+                        let to: usize = ask(&format!("Nueva posición (1-{}):", pipeline.len()), from + 1);
+                        let to = to.clamp(1, pipeline.len()) - 1;
+                        let step = pipeline.remove(from);
+                        pipeline.insert(to, step);
+                    }
+                }
+                Ok(PREVIEW) => {
+                    if let Some(img) = preview {
+                        if let Err(e) = Self::preview(img, &pipeline) {
+                            Interface::error(&format!("No se pudo previsualizar: {}", e));
+                        }
+                    }
+                }
+                _ => break,
+            }
+        }
+        pipeline
+    }
+
+    /// Muestra la receta actual numerada.
+    pub fn print(pipeline: &[PipelineStep]) {
+        if pipeline.is_empty() {
+            Interface::info("Receta vacía.");
+            return;
+        }
+        Interface::info(&format!("Receta actual ({} pasos):", pipeline.len()));
+        for (i, step) in pipeline.iter().enumerate() {
+            println!("   {}. {}", i + 1, step);
+        }
+    }
+
+    fn pick_option() -> Option<MenuOption> {
+        let categorias: Vec<&str> = CATEGORIES.iter().map(|c| c.title).collect();
+        let titulo = Select::new("Añadir paso al pipeline:", categorias).prompt().ok()?;
+        let categoria = CATEGORIES.iter().find(|c| c.title == titulo)?;
+        Select::new(categoria.prompt, categoria.options.to_vec()).prompt().ok()
+    }
+
+    fn pick_step(pipeline: &[PipelineStep], prompt: &str) -> Option<usize> {
+        let labels: Vec<String> = pipeline
+            .iter()
+            .enumerate()
+            .map(|(i, step)| format!("{}. {}", i + 1, step))
+            .collect();
+        let choice = Select::new(prompt, labels.clone()).prompt().ok()?;
+        labels.iter().position(|l| *l == choice)
+    }
+
+    /// Ejecuta la receta actual sobre la imagen cargada, la guarda en un archivo temporal
+    /// y, si se pide, la muestra en una ventana.
+    fn preview(img: &Mat, pipeline: &[PipelineStep]) -> opencv::Result<()> {
+        let Some(steps) = CommandManager::validar_receta(pipeline, ImageFormat::of(img)) else {
+            return Ok(());
+        };
+
+        let options = PipelineOptions::from_config(&AppConfig::load());
+        let res = DynamicPipeline::run_with(img, &steps, &options)?;
+        CommandManager::print_result(&res);
+
+        let path = std::env::temp_dir().join("nixvision_preview.jpg");
+        FrameCapture::write_image(&res.image, &path.to_string_lossy())?;
+        Interface::success(&format!("Vista previa guardada en {:?}", path));
+
+        let mostrar = Confirm::new("¿Mostrar en una ventana?").with_default(false).prompt().unwrap_or(false);
+        if mostrar {
+            highgui::imshow("NixVision - Vista previa", &res.image)?;
+            Interface::info("Pulsa cualquier tecla sobre la ventana para continuar...");
+            highgui::wait_key(0)?;
+            highgui::destroy_window("NixVision - Vista previa")?;
+        }
+        Ok(())
+    }
+
+    /// Construye el paso y vuelve a pedir sus parámetros mientras el filtro los rechace.
+    pub fn build_valid(option: MenuOption, current: Option<&PipelineStep>) -> PipelineStep {
+        loop {
+            let step = option.build(current);
+            match step.check_params() {
+                Ok(()) => return step,
+                Err(e) => Interface::error(&format!("{}. Vuelve a introducir los parámetros.", e)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opencv::{core::{self, Point, Rect, Scalar}, imgproc, prelude::*};
+    use crate::pipelines::validation::RecipeValidator;
+
+    /// Todas las opciones, en el orden de la declaración.
+    const ALL: [MenuOption; 22] = [
+        MenuOption::Grayscale,
+        MenuOption::Hsv,
+        MenuOption::ValueChannel,
+        MenuOption::Normalize,
+        MenuOption::BackgroundCorrection,
+        MenuOption::Clahe,
+        MenuOption::GaussianBlur,
+        MenuOption::MedianBlur,
+        MenuOption::Bilateral,
+        MenuOption::Sharpen,
+        MenuOption::Laplacian,
+        MenuOption::Canny,
+        MenuOption::Threshold,
+        MenuOption::Otsu,
+        MenuOption::Triangle,
+        MenuOption::AdaptiveMean,
+        MenuOption::AdaptiveGaussian,
+        MenuOption::Morphology,
+        MenuOption::Translate,
+        MenuOption::Rotate,
+        MenuOption::Undistort,
+        MenuOption::Contours,
+    ];
+
+    /// Frame BGR de prueba: fondo en degradado, un bloque claro con un agujero pequeño
+    /// (para cierres y umbrales) y una línea de 1 px (para la mediana). Ningún valor llega a
+    /// 0 ni a 255 para que `Normalize` tenga rango que estirar.
+    fn sample() -> Mat {
+        let mut img = Mat::new_rows_cols_with_default(64, 64, core::CV_8UC3, Scalar::all(0.0)).unwrap();
+        for x in 0..64 {
+            let v = 20.0 + x as f64;
+            imgproc::line(&mut img, Point::new(x, 0), Point::new(x, 63), Scalar::new(v, v + 10.0, v + 20.0, 0.0), 1, imgproc::LINE_8, 0).unwrap();
+        }
+        imgproc::rectangle(&mut img, Rect::new(16, 16, 32, 32), Scalar::new(200.0, 180.0, 160.0, 0.0), imgproc::FILLED, imgproc::LINE_8, 0).unwrap();
+        imgproc::rectangle(&mut img, Rect::new(30, 30, 2, 2), Scalar::all(10.0), imgproc::FILLED, imgproc::LINE_8, 0).unwrap();
+        imgproc::line(&mut img, Point::new(4, 56), Point::new(60, 56), Scalar::all(240.0), 1, imgproc::LINE_8, 0).unwrap();
+        img
+    }
+
+    /// Pasos que la opción necesita antes para recibir el formato que espera.
+    fn prefix(option: MenuOption) -> Vec<PipelineStep> {
+        match option {
+            MenuOption::ValueChannel => vec![PipelineStep::Hsv],
+            MenuOption::Normalize
+            | MenuOption::BackgroundCorrection
+            | MenuOption::Clahe
+            | MenuOption::Laplacian
+            | MenuOption::Canny
+            | MenuOption::Threshold
+            | MenuOption::Otsu
+            | MenuOption::Triangle
+            | MenuOption::AdaptiveMean
+            | MenuOption::AdaptiveGaussian => vec![PipelineStep::Grayscale],
+            MenuOption::Contours => vec![PipelineStep::Grayscale, PipelineStep::Otsu { invert: false }],
+            _ => Vec::new(),
+        }
+    }
+
+    fn differs(a: &Mat, b: &Mat) -> bool {
+        if a.size().unwrap() != b.size().unwrap() || a.typ() != b.typ() {
+            return true;
+        }
+        core::norm2(a, b, core::NORM_INF, &core::no_array()).unwrap() > 0.0
+    }
+
+    #[test]
+    fn every_option_is_in_exactly_one_category() {
+        for option in ALL {
+            let count = CATEGORIES.iter().flat_map(|c| c.options).filter(|o| **o == option).count();
+            assert_eq!(count, 1, "{:?} aparece {} veces en el menú", option, count);
+        }
+        let listed: usize = CATEGORIES.iter().map(|c| c.options.len()).sum();
+        assert_eq!(listed, ALL.len());
+    }
+
+    #[test]
+    fn every_option_runs_a_real_operation() {
+        let img = sample();
+        let options = PipelineOptions::default();
+        for option in ALL {
+            // Necesita la calibración de lente de una cámara; sin ella el paso se omite a propósito
+            if option == MenuOption::Undistort {
+                continue;
+            }
+
+            let step = option.default_step();
+            assert_eq!(MenuOption::of(&step), option, "{:?} construye {}", option, step);
+            assert!(step.check_params().is_ok(), "{:?}: parámetros por defecto inválidos", option);
+
+            let prefix = prefix(option);
+            let mut receta = prefix.clone();
+            receta.push(step.clone());
+            let validation = RecipeValidator::check(&receta, ImageFormat::of(&img), false);
+            assert!(validation.is_valid(), "{:?}: {:?}", option, validation.issues);
+
+            let before = DynamicPipeline::run_with(&img, &prefix, &options).unwrap().image;
+            let after = DynamicPipeline::run_with(&img, &receta, &options).unwrap_or_else(|e| panic!("{:?} ({}) falla: {}", option, step, e));
+            assert!(differs(&before, &after.image), "{:?} ({}) no modifica la imagen", option, step);
+            if option == MenuOption::Contours {
+                assert!(after.detected(), "los contornos no detectan el bloque");
+            }
+        }
+    }
+}
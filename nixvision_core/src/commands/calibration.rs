@@ -0,0 +1,298 @@
+use std::path::{Path, PathBuf};
+use inquire::Select;
+use opencv::{core::{Mat, Point2f, Size}, prelude::*};
+use nixvision_lib::filters::lens::{Intrinsics, LensCalibration, LensReport, MIN_VIEWS};
+use nixvision_lib::filters::scale::{Reference, Scale};
+use crate::ui::Interface;
+use crate::commands::CommandManager;
+use crate::config::settings::AppConfig;
+use crate::io::frames::FrameCapture;
+use crate::pipelines::processing::{DynamicPipeline, PipelineOptions};
+use crate::pipelines::step::PipelineStep;
+use crate::pipelines::validation::ImageFormat;
+
+const REFERENCE_OBJECT: &str = "📏 Objeto de referencia (longitud conocida)";
+const REFERENCE_MARKER: &str = "🔲 Marcador de referencia (cuadrado o círculo)";
+const TWO_POINTS: &str = "📍 Dos puntos con distancia conocida";
+const MANUAL: &str = "✏️ Escala manual (mm/px)";
+const CLEAR: &str = "🗑️ Borrar calibración";
+
+/// Calibración de cada cámara: escala física (mm por píxel) y distorsión de lente.
+pub struct Calibration;
+
+impl Calibration {
+    /// Asistente interactivo: calcula la escala de la cámara `camera` y la guarda en la configuración.
+    pub fn run(camera: &str) -> opencv::Result<()> {
+        let config = AppConfig::load();
+        let Some(cam) = config.camera(camera) else {
+            Interface::error(&format!("La cámara '{}' no está configurada.", camera));
+            return Ok(());
+        };
+        match cam.mm_per_pixel {
+            Some(mm) => Interface::info(&format!("Escala actual de '{}': {:.5} mm/px ({:.2} px/mm)", cam.name, mm, 1.0 / mm)),
+            None => Interface::info(&format!("'{}' no está calibrada: las medidas se dan en píxeles.", cam.name)),
+        }
+
+        let metodos = vec![REFERENCE_OBJECT, REFERENCE_MARKER, TWO_POINTS, MANUAL, CLEAR];
+        let Ok(metodo) = Select::new("Método de calibración:", metodos).prompt() else { return Ok(()) };
+
+        let scale = match metodo {
+            REFERENCE_OBJECT | REFERENCE_MARKER => {
+                let reference = if metodo == REFERENCE_OBJECT {
+                    Reference::Length
+                } else {
+                    let formas = vec!["Cuadrado (lado)", "Círculo (diámetro)"];
+                    match Select::new("Forma del marcador:", formas).prompt() {
+                        Ok("Cuadrado (lado)") => Reference::Square,
+                        Ok(_) => Reference::Circle,
+                        Err(_) => return Ok(()),
+                    }
+                };
+                let Some(img) = Self::reference_image(&cam.url)? else { return Ok(()) };
+
+                // La referencia debe ser el objeto principal de la receta
+                Interface::info("Construye o elige una receta que detecte la referencia como objeto principal.");
+                let receta = CommandManager::seleccionar_receta(Some(&img));
+                let Some(receta) = CommandManager::validar_receta(&receta, ImageFormat::of(&img)) else {
+                    return Ok(());
+                };
+                let size_mm = Self::ask_mm(match reference {
+                    Reference::Length => "Longitud real de la referencia (mm):",
+                    Reference::Square => "Lado real del marcador (mm):",
+                    Reference::Circle => "Diámetro real del marcador (mm):",
+                });
+                Self::from_reference(&img, &receta, reference, size_mm, cam.intrinsics.as_ref())?
+            }
+            TWO_POINTS => {
+                Interface::info("Coordenadas en píxeles de la imagen (p. ej. medidas sobre una captura).");
+                let a = Self::ask_point("Punto 1 (x,y):");
+                let b = Self::ask_point("Punto 2 (x,y):");
+                let distance = Self::ask_mm("Distancia real entre los puntos (mm):");
+                Scale::from_points(a, b, distance).map_err(|e| e.to_string())
+            }
+            MANUAL => {
+                let raw = Interface::ask_text("mm por píxel:", &cam.mm_per_pixel.map(|v| v.to_string()).unwrap_or_default());
+                raw.trim()
+                    .parse()
+                    .map_err(|_| format!("'{}' no es un valor válido", raw))
+                    .and_then(|v| Scale::new(v).map_err(|e| e.to_string()))
+            }
+            _ => {
+                match Self::save(camera, None) {
+                    Ok(()) => Interface::success(&format!("Calibración de '{}' eliminada.", camera)),
+                    Err(e) => Interface::error(&e),
+                }
+                return Ok(());
+            }
+        };
+
+        match scale.and_then(|scale| Self::save(camera, Some(scale)).map(|()| scale)) {
+            Ok(scale) => Interface::success(&format!(
+                "Escala de '{}' guardada: {:.5} mm/px ({:.2} px/mm)",
+                camera, scale.mm_per_pixel, 1.0 / scale.mm_per_pixel
+            )),
+            Err(e) => Interface::error(&format!("No se pudo calibrar: {}", e)),
+        }
+        Ok(())
+    }
+
+    /// Ejecuta `receta` sobre `img` con la calibración de lente de la cámara y calcula la
+    /// escala a partir del objeto principal, igual que se medirá después en `process`.
+    /// Los errores de OpenCV se propagan; los de calibración van en el `Err` interno.
+    pub(crate) fn from_reference(
+        img: &Mat,
+        receta: &[PipelineStep],
+        reference: Reference,
+        size_mm: f64,
+        intrinsics: Option<&Intrinsics>,
+    ) -> opencv::Result<Result<Scale, String>> {
+        let undistort = receta.iter().any(|s| matches!(s, PipelineStep::Undistort));
+        if undistort && intrinsics.is_none() {
+            return Ok(Err("la receta corrige la lente pero la cámara no tiene calibración de lente; calibra la lente primero".to_string()));
+        }
+        if !undistort && intrinsics.is_some() {
+            Interface::warning("La cámara tiene calibración de lente y la receta no la corrige: usa la misma receta (sin `undistort`) al medir.");
+        }
+
+        let options = PipelineOptions { intrinsics: intrinsics.cloned(), ..PipelineOptions::default() };
+        let res = DynamicPipeline::run_with(img, receta, &options)?;
+        CommandManager::print_result(&res);
+        if res.count() > 1 {
+            Interface::warning("Hay varios objetos: se usa el principal (#1) como referencia.");
+        }
+        Ok(match res.primary() {
+            Some(reference_object) => Scale::from_reference(reference_object, reference, size_mm).map_err(|e| e.to_string()),
+            None => Err("la receta no detectó ningún objeto de referencia".to_string()),
+        })
+    }
+
+    /// Guarda (o borra, con `None`) la escala de la cámara en la configuración global.
+    pub(crate) fn save(camera: &str, scale: Option<Scale>) -> Result<(), String> {
+        let mut config = AppConfig::load();
+        let cam = config
+            .cameras
+            .iter_mut()
+            .find(|c| c.name == camera)
+            .ok_or_else(|| format!("La cámara '{}' no está configurada", camera))?;
+        cam.mm_per_pixel = scale.map(|s| s.mm_per_pixel);
+        config.save().map_err(|e| format!("Error al guardar la configuración: {}", e))
+    }
+
+    /// Asistente interactivo: calcula intrínsecos y distorsión de `camera` a partir de una
+    /// carpeta de capturas de un tablero de ajedrez y los guarda en la configuración.
+    pub fn lens(camera: &str) -> opencv::Result<()> {
+        let config = AppConfig::load();
+        let Some(cam) = config.camera(camera) else {
+            Interface::error(&format!("La cámara '{}' no está configurada.", camera));
+            return Ok(());
+        };
+        match &cam.intrinsics {
+            Some(i) => Interface::info(&format!(
+                "Lente de '{}' calibrada para {}x{} (error RMS {:.3} px).",
+                cam.name, i.width, i.height, i.rms
+            )),
+            None => Interface::info(&format!("La lente de '{}' no está calibrada.", cam.name)),
+        }
+
+        let opciones = vec!["📐 Calibrar con capturas de un tablero", CLEAR];
+        match Select::new("Calibración de lente:", opciones).prompt() {
+            Ok(CLEAR) => {
+                match Self::save_lens(camera, None) {
+                    Ok(()) => Interface::success(&format!("Calibración de lente de '{}' eliminada.", camera)),
+                    Err(e) => Interface::error(&e),
+                }
+                return Ok(());
+            }
+            Ok(_) => {}
+            Err(_) => return Ok(()),
+        }
+
+        Interface::info(&format!(
+            "Usa al menos {} capturas del tablero (p. ej. de 'Frame Capture') en posiciones e inclinaciones distintas.",
+            MIN_VIEWS
+        ));
+        let folder = Interface::ask_text("Carpeta con las capturas:", &format!("Capturas/{}", cam.name.replace(" ", "_")));
+        let pattern = loop {
+            let raw = Interface::ask_text("Esquinas interiores del tablero (columnas x filas):", "9x6");
+            match Self::parse_pattern(&raw) {
+                Some(pattern) => break pattern,
+                None => Interface::error(&format!("'{}' no es un patrón válido (formato 9x6).", raw)),
+            }
+        };
+        let square_mm = Self::ask_mm("Lado de cada casilla (mm):");
+
+        match Self::calibrate_lens(Path::new(folder.trim()), pattern, square_mm)
+            .and_then(|report| Self::save_lens(camera, Some(report.intrinsics.clone())).map(|()| report))
+        {
+            Ok(report) => Interface::success(&format!(
+                "Lente de '{}' guardada: error RMS {:.3} px con {} capturas.",
+                camera,
+                report.intrinsics.rms,
+                report.used.len()
+            )),
+            Err(e) => Interface::error(&format!("No se pudo calibrar la lente: {}", e)),
+        }
+        Ok(())
+    }
+
+    /// Carga las imágenes de `folder` y calibra la lente; informa de las capturas sin tablero.
+    pub(crate) fn calibrate_lens(folder: &Path, pattern: Size, square_mm: f64) -> Result<LensReport, String> {
+        let paths = Self::board_images(folder)?;
+        let mut images = Vec::with_capacity(paths.len());
+        for path in &paths {
+            let img = FrameCapture::load_image(&path.to_string_lossy()).map_err(|e| e.to_string())?;
+            if img.empty() {
+                return Err(format!("no se pudo cargar '{}'", path.display()));
+            }
+            images.push(img);
+        }
+
+        Interface::info(&format!("🔍 Buscando el tablero {}x{} en {} capturas...", pattern.width, pattern.height, images.len()));
+        let report = LensCalibration::calibrate(&images, pattern, square_mm).map_err(|e| e.to_string())?;
+        for (i, path) in paths.iter().enumerate().filter(|(i, _)| !report.used.contains(i)) {
+            Interface::warning(&format!("Tablero no encontrado en '{}' (captura {}): se descarta.", path.display(), i));
+        }
+        if report.intrinsics.rms > 1.0 {
+            Interface::warning("Error RMS alto: repite las capturas con el tablero plano, nítido y en más posiciones.");
+        }
+        Ok(report)
+    }
+
+    /// Guarda (o borra, con `None`) la calibración de lente de la cámara.
+    pub(crate) fn save_lens(camera: &str, intrinsics: Option<Intrinsics>) -> Result<(), String> {
+        let mut config = AppConfig::load();
+        let cam = config
+            .cameras
+            .iter_mut()
+            .find(|c| c.name == camera)
+            .ok_or_else(|| format!("La cámara '{}' no está configurada", camera))?;
+        cam.intrinsics = intrinsics;
+        config.save().map_err(|e| format!("Error al guardar la configuración: {}", e))
+    }
+
+    /// `"9x6"` -> esquinas interiores por fila y por columna.
+    pub(crate) fn parse_pattern(raw: &str) -> Option<Size> {
+        let raw = raw.trim().to_lowercase();
+        let (cols, rows) = raw.split_once('x')?;
+        Some(Size::new(cols.trim().parse().ok()?, rows.trim().parse().ok()?))
+    }
+
+    /// Imágenes `.jpg`/`.jpeg`/`.png` de la carpeta, ordenadas por nombre.
+    fn board_images(folder: &Path) -> Result<Vec<PathBuf>, String> {
+        let entries = std::fs::read_dir(folder).map_err(|e| format!("no se pudo leer '{}': {}", folder.display(), e))?;
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| {
+                path.extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| matches!(ext.to_lowercase().as_str(), "jpg" | "jpeg" | "png"))
+            })
+            .collect();
+        if paths.is_empty() {
+            return Err(format!("'{}' no contiene imágenes", folder.display()));
+        }
+        paths.sort();
+        Ok(paths)
+    }
+
+    /// `"x,y"` -> punto.
+    fn parse_point(raw: &str) -> Option<Point2f> {
+        let (x, y) = raw.split_once(',')?;
+        Some(Point2f::new(x.trim().parse().ok()?, y.trim().parse().ok()?))
+    }
+
+    /// Imagen de un archivo o, con la ruta vacía, un frame de la cámara.
+    fn reference_image(url: &str) -> opencv::Result<Option<Mat>> {
+        let path = Interface::ask_text("Imagen con la referencia (vacío = capturar de la cámara):", "");
+        let img = if path.trim().is_empty() {
+            FrameCapture::capture_from_stream(url)?
+        } else {
+            FrameCapture::load_image(path.trim())?
+        };
+        if img.empty() {
+            Interface::error("No se pudo obtener la imagen de referencia.");
+            return Ok(None);
+        }
+        Ok(Some(img))
+    }
+
+    fn ask_point(prompt: &str) -> Point2f {
+        loop {
+            let raw = Interface::ask_text(prompt, "");
+            match Self::parse_point(&raw) {
+                Some(point) => return point,
+                None => Interface::error(&format!("'{}' no es un punto válido (formato x,y).", raw)),
+            }
+        }
+    }
+
+    fn ask_mm(prompt: &str) -> f64 {
+        loop {
+            let raw = Interface::ask_text(prompt, "");
+            match raw.trim().parse::<f64>() {
+                Ok(value) if value > 0.0 => return value,
+                _ => Interface::error(&format!("'{}' no es una medida válida.", raw)),
+            }
+        }
+    }
+}
@@ -1,10 +1,35 @@
-use inquire::Select;
+pub mod builder;
+pub mod calibration;
+
+use inquire::{Confirm, Select};
+use opencv::core::Mat;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
 use crate::ui::Interface;
 use crate::io::frames::FrameCapture;
+use crate::io::report::SweepReport;
 use crate::config::settings::{AppConfig,CameraSettings};
-use crate::pipelines::processing::DynamicPipeline;
-
-
+use crate::config::recipes::{Recipe, RecipeStore};
+use crate::pipelines::cache::PrefixCache;
+use crate::pipelines::parallel::{run_batch, Progress};
+use crate::pipelines::processing::{DynamicPipeline, PipelineOptions, ProcessResult};
+use crate::pipelines::profiler::Profiler;
+use crate::pipelines::step::PipelineStep;
+use crate::pipelines::scoring::{RankedCandidate, Scorer};
+use crate::pipelines::sweep::{Sweep, SweepResult, SweepSpec};
+use crate::pipelines::validation::{ImageFormat, RecipeValidator, Severity, ValidationIssue};
+use builder::PipelineBuilder;
+use calibration::Calibration;
+
+
+/// Resultado de un Auto-Tune: todas las combinaciones, su clasificación y lo que se guardó.
+pub(crate) struct AutoTuneReport {
+    pub results: Vec<SweepResult>,
+    /// Combinaciones con detección, de mejor a peor.
+    pub ranking: Vec<RankedCandidate>,
+    pub best_recipe: Option<PathBuf>,
+    pub profiler: Profiler,
+}
 
 pub struct CommandManager;
 
@@ -13,7 +38,7 @@ impl CommandManager {
         
 
         // 2. Menú de selección
-        let opciones = vec!["Image","Detection", "Camera", "Salir"];
+        let opciones = vec!["Image","Detection", "Camera", "Recipes", "Salir"];
         let seleccion = Select::new("--- PANEL DE CONTROL NIXVISION ---", opciones).prompt();
 
         // 3. Match de opciones (Quitamos el uso de &gui)
@@ -27,6 +52,9 @@ impl CommandManager {
             Ok("Detection") =>{
                 let _ = Self::handle_detection_brute_force();
             }
+            Ok("Recipes") => {
+                Self::handle_recipes_menu();
+            }
 
             Ok("Salir") => println!("Saliendo de NixVision..."),
             _ => println!("Operación cancelada."),
@@ -46,66 +74,166 @@ impl CommandManager {
                 return Ok(());
             }
         };
-        let base_name = input_path.split('.').next().unwrap_or("resultado");
 
-        Interface::info("🚀 Iniciando NixVision Auto-Tune: Búsqueda Exhaustiva con Métricas...");
+        // 2. Definición del barrido (vacío = búsqueda clásica Blur/Canny/Área)
+        let spec_path = Interface::ask_text("> Archivo de barrido (.toml/.json, vacío = Auto-Tune clásico):", "");
+        let spec = if spec_path.trim().is_empty() {
+            SweepSpec::autotune_default()
+        } else {
+            match SweepSpec::load(Path::new(spec_path.trim())) {
+                Ok(spec) => spec,
+                Err(e) => {
+                    Interface::error(&format!("No se pudo cargar el barrido: {}", e));
+                    return Ok(());
+                }
+            }
+        };
+        let sweep = match Sweep::new(spec) {
+            Ok(sweep) => sweep,
+            Err(e) => {
+                Interface::error(&format!("Barrido inválido: {}", e));
+                return Ok(());
+            }
+        };
+        if !Self::validar_barrido(&sweep, ImageFormat::of(&img)) {
+            return Ok(());
+        }
 
-        let blur_sizes = [3, 5, 7, 9, 11, 13]; 
-        let canny_ranges = [
-            (10.0, 40.0), (20.0, 60.0), (30.0, 80.0),
-            (50.0, 100.0), (70.0, 150.0), (80.0, 200.0),
-            (40.0, 50.0), (60.0, 70.0), (90.0, 100.0)
-        ];
-        let min_areas = [100.0, 300.0, 500.0, 1000.0, 2000.0, 4000.0];
+        let base_name = Self::output_prefix(&input_path);
+        let base_name = base_name.as_str();
+        let mut options = Self::preguntar_depuracion(&format!("{}_debug", base_name));
+        let profile = Confirm::new("¿Perfilar tiempos por paso?").with_default(false).prompt().unwrap_or(false);
+        Self::preguntar_camara(&mut options);
 
-        let mut total_attempts = 0;
-        let mut success_count = 0;
+        let report = Self::run_autotune(&img, &sweep, base_name, &options, AppConfig::load().threads)?;
+        if profile {
+            Self::print_profile(&report.profiler, None);
+        }
+        Ok(())
+    }
 
-        for &blur in &blur_sizes {
-            for &(low, high) in &canny_ranges {
-                for &area_threshold in &min_areas {
-                    total_attempts += 1;
-                    
-                    let receta = vec![
-                        (1, 1, 0.0, 0.0),               // Grises
-                        (3, 1, blur as f64, 0.0),       // Blur
-                        (4, 3, low, high),              // Canny
-                        (6, 1, area_threshold, 0.0),    // Contornos/Métricas
-                    ];
-
-                    // 🚀 Procesamiento con retorno de metadatos detallados
-                    let res_obj = DynamicPipeline::process_with_metadata(&img, &receta)?;
-
-                    if res_obj.detected {
-                        success_count += 1;
-                        let out_name = format!("{}_B{}_C{}-{}_A{}.jpg", base_name, blur, low, high, area_threshold);
-                        
-                        FrameCapture::save_image(&res_obj.image, &out_name)?;
-                        
-                        // 📊 REPORTE DETALLADO (Igual al modo manual para tu tabla del Punto 5)
-                        println!("\n✅ Objeto Detectado en Intento [{}/324]:", total_attempts);
-                        println!("   - Archivo: {}", out_name);
-                        println!("   - Área: {:.2} px", res_obj.area);
-                        // Si añadiste perimeter, width y height a tu struct ProcessResult:
-                        println!("   - Perímetro: {:.2} px", res_obj.perimeter);
-                        println!("   - Bounding Box: {}x{} px", res_obj.width, res_obj.height);
-                        println!("--------------------------------------------------");
-                    } else {
-                        print!(".");
-                        use std::io::{self, Write};
-                        io::stdout().flush().unwrap();
-                    }
-                }
+    /// Prefijo de los archivos de salida de una imagen: su ruta sin extensión, para que los
+    /// resultados queden junto a ella (`./fotos/limon.jpg` -> `./fotos/limon`).
+    pub(crate) fn output_prefix(input: &str) -> String {
+        let path = Path::new(input.trim());
+        let stem = path.file_stem().map_or("resultado".into(), |s| s.to_string_lossy());
+        path.parent().unwrap_or(Path::new("")).join(&*stem).to_string_lossy().into_owned()
+    }
+
+    /// Ejecuta todas las combinaciones de `sweep` sobre `img`, las puntúa según `[scoring]`
+    /// y guarda solo las `top_n` mejores como `{base_name}_top{N}_{etiqueta}.jpg`,
+    /// más la receta ganadora en `{base_name}_best.toml` y el informe de todos los intentos
+    /// en `{base_name}_report.{csv,json,html}`.
+    /// Con `options.debug_dir`, cada combinación vuelca sus etapas en una subcarpeta propia;
+    /// con `options.scale`, las imágenes y el informe incluyen las medidas en mm.
+    /// Las combinaciones se reparten en `threads` hilos (0 = todos los núcleos).
+    pub(crate) fn run_autotune(
+        img: &Mat,
+        sweep: &Sweep,
+        base_name: &str,
+        options: &PipelineOptions,
+        threads: usize,
+    ) -> opencv::Result<AutoTuneReport> {
+        let scale = options.scale;
+        let total = sweep.total();
+        Interface::info(&format!(
+            "🚀 Iniciando NixVision Auto-Tune '{}': {} combinaciones...",
+            sweep.spec.name, total
+        ));
+
+        // 1. Barrido completo en paralelo (sin guardar imágenes: se decide tras puntuar)
+        let profiler = Mutex::new(Profiler::new());
+        let progress = Progress::new(sweep.runnable());
+        let cache = PrefixCache::new(sweep.cache_capacity(img));
+        let results = sweep.run(img, options, threads, &cache, |_, res_obj| {
+            if let Some(res_obj) = res_obj {
+                profiler.lock().unwrap_or_else(PoisonError::into_inner).record(res_obj);
             }
+            progress.tick(Interface::progress_bar);
+            Ok(())
+        })?;
+        let profiler = profiler.into_inner().unwrap_or_else(PoisonError::into_inner);
+        println!("\n");
+        if cache.hits() > 0 {
+            Interface::info(&format!(
+                "♻️ Caché de etapas: {} de {} ejecuciones reutilizaron pasos previos ({} imágenes intermedias).",
+                cache.hits(), cache.hits() + cache.misses(), cache.stored()
+            ));
         }
 
-        println!("\n");
+        let skipped = results.iter().filter(|r| r.error.is_some()).count();
+        if skipped > 0 {
+            Interface::warning(&format!("{} combinaciones omitidas por parámetros inválidos o errores de OpenCV.", skipped));
+        }
+
+        // 2. Puntuación y selección
+        let scoring = &sweep.spec.scoring;
+        let ranking = Scorer::rank(img, &results, scoring)?;
         Interface::success(&format!(
-            "¡Auto-Tune completado! Se analizaron {} combinaciones. {} imágenes con métricas generadas.", 
-            total_attempts, success_count
+            "¡Auto-Tune completado! Se analizaron {} combinaciones; {} detectaron objetos.",
+            total, ranking.len()
         ));
-        
-        Ok(())
+
+        // 3. Solo las mejores se vuelven a ejecutar para guardar su imagen
+        let mut saved = Vec::new();
+        let top_options = PipelineOptions { debug_dir: None, montage: false, ..options.clone() };
+        for (rank, ranked) in ranking.iter().take(scoring.top_n).enumerate() {
+            let result = &results[ranked.result];
+            let out_name = format!("{}_top{}_{}.jpg", base_name, rank + 1, result.candidate.label());
+            let res_obj = DynamicPipeline::run_cached(img, &result.candidate.recipe, &top_options, &cache)?;
+            FrameCapture::write_image(&res_obj.image, &out_name)?;
+
+            let score = &ranked.score;
+            println!("\n🏅 #{} — puntuación {:.3} (Intento [{}/{}])", rank + 1, score.total, result.candidate.index + 1, total);
+            println!("   - Archivo: {}", out_name);
+            println!(
+                "   - Solidez: {:.2} | Área: {:.2} | Estabilidad: {:.2} | Nitidez: {:.2}",
+                score.solidity, score.area, score.stability, score.sharpness
+            );
+            if let Some(primary) = &result.primary {
+                println!("   - Área: {:.2} px | Perímetro: {:.2} px | Bounding Box: {}x{} px",
+                    primary.area, primary.perimeter, primary.width, primary.height);
+                if let Some(mm) = scale.map(|s| s.measure(primary)) {
+                    println!("   - Área: {:.2} mm² | Perímetro: {:.2} mm | Bounding Box: {:.2}x{:.2} mm",
+                        mm.area_mm2, mm.perimeter_mm, mm.width_mm, mm.height_mm);
+                }
+            }
+            println!("--------------------------------------------------");
+            saved.push(out_name);
+        }
+
+        // 4. Receta ganadora, lista para `process --recipe`
+        let mut best_recipe = None;
+        if let Some(best) = ranking.first() {
+            let candidate = &results[best.result].candidate;
+            let stem = Path::new(base_name).file_name().map_or(base_name.into(), |n| n.to_string_lossy());
+            let mut recipe = Recipe::new(&format!("{}_best", stem), candidate.recipe.clone());
+            recipe.description = format!("Auto-Tune '{}': {} (puntuación {:.3})", sweep.spec.name, candidate.label(), best.score.total);
+
+            let path = PathBuf::from(format!("{}_best.toml", base_name));
+            match recipe.save(&path) {
+                Ok(()) => {
+                    Interface::success(&format!("Receta ganadora guardada en {:?}", path));
+                    best_recipe = Some(path);
+                }
+                Err(e) => Interface::error(&format!("No se pudo guardar la receta ganadora: {}", e)),
+            }
+        }
+
+        // 5. Informe de todos los intentos
+        let report = SweepReport::new(&sweep.spec.name, &results, &ranking, &saved, scale);
+        for (ext, written) in [
+            ("csv", report.write_csv(Path::new(&format!("{}_report.csv", base_name)))),
+            ("json", report.write_json(Path::new(&format!("{}_report.json", base_name)))),
+            ("html", report.write_html(Path::new(&format!("{}_report.html", base_name)))),
+        ] {
+            match written {
+                Ok(()) => Interface::success(&format!("Informe guardado en {}_report.{}", base_name, ext)),
+                Err(e) => Interface::error(&format!("No se pudo escribir el informe {}: {}", ext, e)),
+            }
+        }
+
+        Ok(AutoTuneReport { results, ranking, best_recipe, profiler })
     }
 
     fn handle_img() -> opencv::Result<()> {
@@ -113,12 +241,21 @@ impl CommandManager {
         let input_path = Interface::ask_text("> Ruta de la imagen de origen:", "fruta.jpg");
         let img = FrameCapture::load_image(&input_path)?;
 
-        // 2. Configurar la "receta" del pipeline (Pide parámetros solo si se eligen)
-        let receta = Self::construir_pipeline_usuario();
+        // 2. Configurar la "receta" del pipeline (guardada o construida en el momento)
+        let receta = Self::seleccionar_receta(Some(&img));
+        let Some(receta) = Self::validar_receta(&receta, ImageFormat::of(&img)) else {
+            return Ok(());
+        };
 
-        // 3. Procesar la imagen con los parámetros dinámicos
+        // 3. Procesar la imagen con los parámetros dinámicos (opcionalmente volcando cada etapa)
+        let mut options = Self::preguntar_depuracion("debug_etapas");
+        Self::preguntar_camara(&mut options);
         Interface::info("Procesando imagen con NixVision Core...");
-        let res = DynamicPipeline::process(&img, &receta)?;
+        let res = DynamicPipeline::run_with(&img, &receta, &options)?;
+        Self::print_result(&res);
+        if let Some(dir) = &options.debug_dir {
+            Interface::info(&format!("Etapas intermedias guardadas en {:?}", dir));
+        }
 
         // 4. Nombre de salida (Se guarda en la carpeta actual donde ejecutas el CLI)
         let out_name = Interface::ask_text("> Nombre del archivo de salida:", "resultado_procesado.jpg");
@@ -131,7 +268,7 @@ impl CommandManager {
         println!(); 
 
         // Guarda directamente en la ruta de ejecución
-        FrameCapture::save_image(&res, &out_name)?;
+        FrameCapture::write_image(&res.image, &out_name)?;
         Interface::success(&format!("Imagen guardada correctamente como: {}", out_name));
         
         Ok(())
@@ -140,11 +277,17 @@ impl CommandManager {
 
     fn handle_capture_frame(cam: &crate::config::settings::CameraSettings) -> opencv::Result<()> {
         // 1. Configurar la "receta" del pipeline
-        let receta: Vec<(i32, i32, f64, f64)> = Self::construir_pipeline_usuario();
+        let receta: Vec<PipelineStep> = Self::seleccionar_receta(None);
+        // Validamos antes de abrir la cámara: los frames llegan como BGR de 8 bits
+        let Some(receta) = Self::validar_receta(&receta, ImageFormat::bgr8()) else {
+            return Ok(());
+        };
 
         // 2. Parámetros de captura
         let fps: f64 = Interface::ask_text("FPS de captura:", "10.0").parse().unwrap_or(10.0);
         let segundos: u64 = Interface::ask_text("Segundos de duración:", "2").parse().unwrap_or(2);
+        let profile = Confirm::new("¿Perfilar tiempos por paso?").with_default(false).prompt().unwrap_or(false);
+        let profiler = Mutex::new(Profiler::new());
 
         // 🔥 3. Gestión de Carpeta Dinámica
         // Primero preguntamos por el nombre de la carpeta raíz (ej. "Practica2" o "Pruebas_Lunes")
@@ -169,109 +312,288 @@ impl CommandManager {
         // 4. Obtener frames (Paso de Adquisición) [cite: 8]
         let frames = FrameCapture::capture_sequence(&cam.url, fps, segundos)?;
 
-        // 5. Procesar y guardar cada imagen dentro de la carpeta seleccionada
-        for (i, frame) in frames.iter().enumerate() {
+        // 5. Procesar y guardar cada imagen dentro de la carpeta seleccionada (en paralelo)
+        let progress = Progress::new(frames.len());
+        let config = AppConfig::load();
+        let options = PipelineOptions {
+            scale: cam.scale(),
+            intrinsics: cam.intrinsics.clone(),
+            ..PipelineOptions::from_config(&config)
+        };
+        let counts = run_batch(&frames, &receta, &options, config.threads, |i, procesada| {
             // El pipeline procesa el frame según la receta elegida [cite: 5]
-            let procesada = DynamicPipeline::process(frame, &receta)?; 
-            
+            profiler.lock().unwrap_or_else(PoisonError::into_inner).record(procesada);
+
             // Formato: nombre_sesion/nombre_camara/frame_001.jpg
             let filename = format!("{}/frame_{:03}.jpg", folder, i);
-            FrameCapture::save_image(&procesada, &filename)?;
-            Interface::progress_bar(i + 1, frames.len());
-        }
+            FrameCapture::write_image(&procesada.image, &filename)?;
+            progress.tick(Interface::progress_bar);
+            Ok(procesada.count())
+        })?;
+        let profiler = profiler.into_inner().unwrap_or_else(PoisonError::into_inner);
 
         Interface::success(&format!("\nProceso completado. Archivos en: {}", folder));
+        if receta.iter().any(|s| matches!(s, PipelineStep::Contours { .. })) {
+            Interface::object_counts(&counts);
+        }
+        if profile {
+            let csv = Path::new(&folder).join("profile.csv");
+            Self::print_profile(&profiler, Some(csv.as_path()));
+        }
         Ok(())
     }
 
 
-    fn construir_pipeline_usuario() -> Vec<(i32, i32, f64, f64)> {
-        let mut pipeline = Vec::new();
-        let categorias = vec![
-            "1. Color", 
-            "2. Iluminación", 
-            "3. Ruido", 
-            "4. Bordes/Canny", 
-            "5. Geometría (Afín)", 
-            "6. Contornos (Métricas)",
-            "🚀 Finalizar y Procesar"
-        ];
-
-        loop {
-            let seleccion = Select::new("Añadir paso al pipeline:", categorias.clone()).prompt();
-            
-            match seleccion {
-                Ok("1. Color") => {
-                    let opt = Select::new("Conversión:", vec!["Grises", "HSV"]).prompt();
-                    if let Ok(o) = opt { 
-                        pipeline.push((1, if o == "Grises" { 1 } else { 2 }, 0.0, 0.0)); 
+    /// Muestra en consola las advertencias y los objetos detectados en una ejecución:
+    /// el detalle completo si hay uno solo, una tabla con el recuento si hay varios.
+    pub(crate) fn print_result(res: &ProcessResult) {
+        for warning in &res.warnings {
+            Interface::warning(warning);
+        }
+        match res.objects.as_slice() {
+            [] => {}
+            [only] => Interface::detection(only),
+            many => Interface::objects(many),
+        }
+    }
+
+    /// Pregunta si se quieren volcar las etapas intermedias y dónde.
+    fn preguntar_depuracion(default_dir: &str) -> PipelineOptions {
+        let activar = Confirm::new("¿Guardar etapas intermedias (modo depuración)?")
+            .with_default(false)
+            .prompt()
+            .unwrap_or(false);
+        let options = PipelineOptions::from_config(&AppConfig::load());
+        if !activar {
+            return options;
+        }
+
+        let dir = Interface::ask_text("Carpeta de depuración:", default_dir);
+        let montage = Confirm::new("¿Generar montaje lado a lado?").with_default(true).prompt().unwrap_or(true);
+        PipelineOptions {
+            debug_dir: Some(PathBuf::from(dir)),
+            montage,
+            ..options
+        }
+    }
+
+    /// Calibración (escala en mm y lente) de la cámara que tomó la imagen; no pregunta
+    /// si ninguna cámara está calibrada.
+    fn preguntar_camara(options: &mut PipelineOptions) {
+        let config = AppConfig::load();
+        let calibradas: Vec<&CameraSettings> = config
+            .cameras
+            .iter()
+            .filter(|c| c.scale().is_some() || c.intrinsics.is_some())
+            .collect();
+        if calibradas.is_empty() {
+            return;
+        }
+
+        let mut opciones = vec!["Sin calibración (píxeles)".to_string()];
+        opciones.extend(calibradas.iter().map(|c| {
+            let scale = c.scale().map(|s| format!("{:.5} mm/px", s.mm_per_pixel));
+            let lens = c.intrinsics.as_ref().map(|_| "lente".to_string());
+            format!("📷 {} ({})", c.name, [scale, lens].into_iter().flatten().collect::<Vec<_>>().join(", "))
+        }));
+        let Ok(choice) = Select::new("¿Cámara que tomó la imagen?", opciones.clone()).prompt() else { return };
+        let Some(cam) = opciones.iter().position(|o| *o == choice).and_then(|i| i.checked_sub(1)).map(|i| calibradas[i]) else {
+            return;
+        };
+        options.scale = cam.scale();
+        options.intrinsics = cam.intrinsics.clone();
+    }
+
+    /// Imprime el resumen de tiempos y, si se indica, lo exporta a CSV.
+    pub(crate) fn print_profile(profiler: &Profiler, csv: Option<&Path>) {
+        if profiler.frames() == 0 {
+            Interface::info("No hay tiempos que mostrar.");
+            return;
+        }
+        Interface::info(&format!("⏱️ Tiempos de {} ejecuciones del pipeline:", profiler.frames()));
+        Interface::timing_table(&profiler.summary());
+
+        if let Some(path) = csv {
+            match profiler.write_csv(path) {
+                Ok(()) => Interface::success(&format!("Perfil exportado a {:?}", path)),
+                Err(e) => Interface::error(&format!("No se pudo exportar el perfil: {}", e)),
+            }
+        }
+    }
+
+    /// Valida la receta contra el formato de entrada e informa de cada corrección o problema.
+    /// Devuelve la receta corregida, o `None` si tiene pasos incompatibles.
+    pub(crate) fn validar_receta(receta: &[PipelineStep], input: ImageFormat) -> Option<Vec<PipelineStep>> {
+        let validation = RecipeValidator::check(receta, input, true);
+        Self::print_issues(&validation.issues);
+
+        if validation.is_valid() {
+            Some(validation.steps)
+        } else {
+            Interface::error("La receta no es compatible con la imagen de entrada; corrígela antes de procesar.");
+            None
+        }
+    }
+
+    /// Valida la receta de un barrido (sin correcciones) antes de lanzar sus combinaciones.
+    pub(crate) fn validar_barrido(sweep: &Sweep, input: ImageFormat) -> bool {
+        let validation = sweep.validate(input);
+        Self::print_issues(&validation.issues);
+
+        if !validation.is_valid() {
+            Interface::error("La receta del barrido no es compatible con la imagen de entrada; corrígela en el archivo de barrido.");
+        }
+        validation.is_valid()
+    }
+
+    fn print_issues(issues: &[ValidationIssue]) {
+        for issue in issues {
+            match issue.severity {
+                Severity::Fixed => Interface::info(&format!("🔧 {}", issue)),
+                Severity::Warning => Interface::warning(&issue.to_string()),
+                Severity::Error => Interface::error(&issue.to_string()),
+            }
+        }
+    }
+
+    /// Permite reutilizar una receta guardada, importar una desde archivo o construir una nueva.
+    /// Con `preview`, el editor permite ver el resultado sobre esa imagen antes de procesar.
+    fn seleccionar_receta(preview: Option<&Mat>) -> Vec<PipelineStep> {
+        let mut opciones = vec!["🛠️ Construir nueva".to_string()];
+        opciones.extend(RecipeStore::list().iter().map(|name| format!("📄 {}", name)));
+        opciones.push("📥 Importar desde archivo".to_string());
+
+        match Select::new("Receta del pipeline:", opciones).prompt() {
+            Ok(choice) if choice == "📥 Importar desde archivo" => {
+                let path = Interface::ask_text("Ruta del archivo (.toml/.json):", "receta.toml");
+                match RecipeStore::import(Path::new(&path)) {
+                    Ok(recipe) => {
+                        Interface::success(&format!("Receta '{}' importada.", recipe.name));
+                        recipe.steps
                     }
-                }
-                Ok("2. Iluminación") => {
-                    let opt = Select::new("Método:", vec!["Normalizar", "Background Correction", "CLAHE"]).prompt();
-                    if let Ok(o) = opt { 
-                        let val = match o { "Normalizar" => 1, "Background Correction" => 2, _ => 3 };
-                        let mut p1 = 0.0;
-                        if val == 3 {
-                            p1 = Interface::ask_text("Clip Limit para CLAHE:", "2.0").parse().unwrap_or(2.0);
-                        }
-                        pipeline.push((2, val, p1, 0.0)); 
+                    Err(e) => {
+                        Interface::error(&format!("No se pudo importar la receta: {}", e));
+                        Self::seleccionar_receta(preview)
                     }
                 }
-                Ok("3. Ruido") => {
-                    let opt = Select::new("Filtro:", vec!["Gaussian Blur", "Median Blur", "Bilateral Filter"]).prompt();
-                    if let Ok(o) = opt { 
-                        let val = match o { "Gaussian Blur" => 1, "Median Blur" => 2, _ => 3 };
-                        // Preguntamos el tamaño del kernel (Punto 2 y 31 de la práctica) [cite: 19, 31]
-                        let size = Interface::ask_text("Tamaño del Blur (impar):", "5").parse().unwrap_or(5.0);
-                        pipeline.push((3, val, size, 0.0)); 
+            }
+            Ok(choice) if choice.starts_with("📄 ") => {
+                let name = choice.replace("📄 ", "");
+                match RecipeStore::load(&name) {
+                    Ok(recipe) => {
+                        Self::mostrar_receta(&recipe);
+                        let editar = Confirm::new("¿Editar la receta antes de usarla?")
+                            .with_default(false)
+                            .prompt()
+                            .unwrap_or(false);
+                        if editar { PipelineBuilder::edit(recipe.steps, preview) } else { recipe.steps }
                     }
-                }
-                Ok("4. Bordes/Canny") => {
-                    let opt = Select::new("Algoritmo:", vec!["Sharpen", "Laplacian", "Canny (Bordes)"]).prompt();
-                    if let Ok(o) = opt { 
-                        let val = match o { "Sharpen" => 1, "Laplacian" => 2, _ => 3 };
-                        let mut p1 = 0.0;
-                        let mut p2 = 0.0;
-                        if val == 3 {
-                            // Parámetros dinámicos para el reporte (Punto 20 y 31) 
-                            p1 = Interface::ask_text("Canny Low Threshold:", "50.0").parse().unwrap_or(50.0);
-                            p2 = Interface::ask_text("Canny High Threshold:", "150.0").parse().unwrap_or(150.0);
-                        }
-                        pipeline.push((4, val, p1, p2)); 
+                    Err(e) => {
+                        Interface::error(&format!("No se pudo cargar la receta '{}': {}", name, e));
+                        Self::seleccionar_receta(preview)
                     }
                 }
-                Ok("5. Geometría (Afín)") => {
-                    let opt = Select::new("Transformación:", vec!["Traslación", "Rotación"]).prompt();
-                    if let Ok(o) = opt { 
-                        let (val, p1, p2) = if o == "Traslación" {
-                            let x = Interface::ask_text("X:", "50.0").parse().unwrap_or(50.0);
-                            let y = Interface::ask_text("Y:", "50.0").parse().unwrap_or(50.0);
-                            (1, x, y)
-                        } else {
-                            let ang = Interface::ask_text("Ángulo:", "45.0").parse().unwrap_or(45.0);
-                            (2, ang, 0.0)
-                        };
-                        pipeline.push((5, val, p1, p2)); 
+            }
+            _ => {
+                let steps = PipelineBuilder::run(preview);
+                Self::ofrecer_guardar_receta(&steps);
+                steps
+            }
+        }
+    }
+
+    fn ofrecer_guardar_receta(steps: &[PipelineStep]) {
+        if steps.is_empty() {
+            return;
+        }
+        let guardar = Confirm::new("¿Guardar esta receta?").with_default(false).prompt().unwrap_or(false);
+        if !guardar {
+            return;
+        }
+
+        let name = Interface::ask_text("Nombre de la receta:", "mi_receta");
+        match RecipeStore::save(&Recipe::new(&name, steps.to_vec())) {
+            Ok(path) => Interface::success(&format!("Receta guardada en {:?}", path)),
+            Err(e) => Interface::error(&format!("Error al guardar la receta: {}", e)),
+        }
+    }
+
+    fn mostrar_receta(recipe: &Recipe) {
+        Interface::info(&format!("Receta '{}' ({} pasos)", recipe.name, recipe.steps.len()));
+        if !recipe.description.is_empty() {
+            println!("   {}", recipe.description);
+        }
+        for (i, step) in recipe.steps.iter().enumerate() {
+            println!("   {}. {}", i + 1, step);
+        }
+    }
+
+    fn handle_recipes_menu() {
+        let mut opciones: Vec<String> = RecipeStore::list()
+            .iter()
+            .map(|name| format!("📄 {}", name))
+            .collect();
+        opciones.push("➕ New Recipe".to_string());
+        opciones.push("📥 Import Recipe".to_string());
+        opciones.push("📤 Export Recipe".to_string());
+        opciones.push("🗑️ Remove Recipe".to_string());
+        opciones.push("🚪 Salir".to_string());
+
+        let seleccion = Select::new("--- Recipes ---", opciones).prompt();
+
+        match seleccion {
+            Ok(choice) if choice == "➕ New Recipe" => {
+                let steps = PipelineBuilder::run(None);
+                let name = Interface::ask_text("Nombre de la receta:", "mi_receta");
+                match RecipeStore::save(&Recipe::new(&name, steps)) {
+                    Ok(path) => Interface::success(&format!("Receta guardada en {:?}", path)),
+                    Err(e) => Interface::error(&format!("Error al guardar la receta: {}", e)),
+                }
+            }
+            Ok(choice) if choice == "📥 Import Recipe" => {
+                let path = Interface::ask_text("Ruta del archivo (.toml/.json):", "receta.toml");
+                match RecipeStore::import(Path::new(&path)) {
+                    Ok(recipe) => Interface::success(&format!("Receta '{}' importada.", recipe.name)),
+                    Err(e) => Interface::error(&format!("No se pudo importar la receta: {}", e)),
+                }
+            }
+            Ok(choice) if choice == "📤 Export Recipe" || choice == "🗑️ Remove Recipe" => {
+                let names = RecipeStore::list();
+                if names.is_empty() {
+                    Interface::info("No hay recetas guardadas.");
+                    return;
+                }
+                let Ok(name) = Select::new("Selecciona la receta:", names).prompt() else { return };
+
+                if choice == "📤 Export Recipe" {
+                    let path = Interface::ask_text("Archivo de destino (.toml/.json):", &format!("{}.toml", name));
+                    match RecipeStore::export(&name, Path::new(&path)) {
+                        Ok(()) => Interface::success(&format!("Receta exportada a {}", path)),
+                        Err(e) => Interface::error(&format!("Error al exportar: {}", e)),
+                    }
+                } else {
+                    match RecipeStore::remove(&name) {
+                        Ok(()) => Interface::success(&format!("Receta '{}' eliminada.", name)),
+                        Err(e) => Interface::error(&format!("Error al eliminar: {}", e)),
                     }
                 }
-                Ok("6. Contornos (Métricas)") => {
-                    // Filtro de área mínima para evitar ruido (Punto 26) [cite: 26]
-                    let area = Interface::ask_text("Área mínima para filtrar:", "500.0").parse().unwrap_or(500.0);
-                    Interface::info("Se ha añadido la extracción de Área y Perímetro.");
-                    pipeline.push((6, 1, area, 0.0)); 
+            }
+            Ok(choice) if choice == "🚪 Salir" => {
+                println!("Regresando...");
+            }
+            Ok(choice) => {
+                let name = choice.replace("📄 ", "");
+                match RecipeStore::load(&name) {
+                    Ok(recipe) => Self::mostrar_receta(&recipe),
+                    Err(e) => Interface::error(&format!("No se pudo cargar la receta '{}': {}", name, e)),
                 }
-                Ok("🚀 Finalizar y Procesar") => break,
-                _ => break,
             }
+            _ => println!("Operación cancelada."),
         }
-        pipeline
     }
 
-    
-
     fn handle_camera_actions(cam: &crate::config::settings::CameraSettings) -> opencv::Result<()> {
-        let opciones = vec!["Frame Capture", "Rotación", "Real-time Vision", "Salir"];
+        let opciones = vec!["Frame Capture", "Calibrar escala", "Calibrar lente", "Rotación", "Real-time Vision", "Salir"];
         let titulo = format!("--- CONTROL: {} ---", cam.name);
         let seleccion = Select::new(&titulo, opciones).prompt();
 
@@ -279,6 +601,12 @@ impl CommandManager {
             Ok("Frame Capture") => {
                 Self::handle_capture_frame(cam)?;
             }
+            Ok("Calibrar escala") => {
+                Calibration::run(&cam.name)?;
+            }
+            Ok("Calibrar lente") => {
+                Calibration::lens(&cam.name)?;
+            }
             
             Ok("Real-time Vision") => {
                 Interface::info("Iniciando flujo RTSP...");
@@ -313,10 +641,7 @@ impl CommandManager {
                 let camera_name = Interface::ask_text("Nombre de la cámara:", "Camara_Dahua");
                 let camera_url = Interface::ask_text("URL o Índice:", "0");
 
-                let new_camera = CameraSettings {
-                    name: camera_name,
-                    url: camera_url,
-                };
+                let new_camera = CameraSettings::new(&camera_name, &camera_url);
 
                 config.cameras.push(new_camera);
 
@@ -0,0 +1,42 @@
+use serde::{de::DeserializeOwned, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Lee un documento `.toml` o `.json` (según la extensión) y lo deserializa.
+pub fn load_document<T: DeserializeOwned>(path: &Path) -> io::Result<T> {
+    let content = fs::read_to_string(path)?;
+
+    if is_json(path) {
+        serde_json::from_str(&content).map_err(|e| invalid_data(path, e))
+    } else {
+        toml::from_str(&content).map_err(|e| invalid_data(path, e))
+    }
+}
+
+/// Serializa `value` en `path`; el formato se decide por la extensión (TOML por defecto).
+pub fn save_document<T: Serialize>(value: &T, path: &Path) -> io::Result<()> {
+    let content = if is_json(path) {
+        serde_json::to_string_pretty(value).map_err(|e| invalid_data(path, e))?
+    } else {
+        toml::to_string_pretty(value).map_err(|e| invalid_data(path, e))?
+    };
+
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() && !parent.exists() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+    fs::write(path, content)
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.eq_ignore_ascii_case("json"))
+        .unwrap_or(false)
+}
+
+fn invalid_data(path: &Path, e: impl std::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("{:?}: {}", path, e))
+}
\ No newline at end of file
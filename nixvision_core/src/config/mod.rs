@@ -1 +1,3 @@
-pub mod settings;
\ No newline at end of file
+pub mod settings;
+pub mod format;
+pub mod recipes;
\ No newline at end of file
@@ -0,0 +1,112 @@
+use serde::{Serialize, Deserialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::config::format::{load_document, save_document};
+use crate::config::settings::AppConfig;
+use crate::pipelines::step::PipelineStep;
+
+/// Receta de pipeline con nombre, lista para guardarse como TOML o JSON.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Recipe {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub steps: Vec<PipelineStep>,
+}
+
+impl Recipe {
+    pub fn new(name: &str, steps: Vec<PipelineStep>) -> Self {
+        Self {
+            name: name.to_string(),
+            description: String::new(),
+            steps,
+        }
+    }
+
+    /// Carga una receta desde un archivo `.toml` o `.json` (según la extensión).
+    pub fn load(path: &Path) -> io::Result<Self> {
+        load_document(path)
+    }
+
+    /// Guarda la receta en `path`; el formato se decide por la extensión (TOML por defecto).
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        save_document(self, path)
+    }
+}
+
+/// Almacén de recetas en `~/.nixvision/recipes/`, junto a `settings.toml`.
+pub struct RecipeStore;
+
+impl RecipeStore {
+    pub fn dir() -> PathBuf {
+        AppConfig::get_config_dir().join("recipes")
+    }
+
+    /// Nombres de las recetas guardadas (sin extensión), ordenados alfabéticamente.
+    pub fn list() -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(Self::dir())
+            .map(|entries| {
+                entries
+                    .filter_map(|e| e.ok())
+                    .map(|e| e.path())
+                    .filter(|p| matches!(p.extension().and_then(|x| x.to_str()), Some("toml") | Some("json")))
+                    .filter_map(|p| p.file_stem().and_then(|s| s.to_str()).map(String::from))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Ruta de una receta guardada; prefiere `.toml` si existen ambos formatos.
+    pub fn path_of(name: &str) -> PathBuf {
+        let toml_path = Self::dir().join(format!("{}.toml", file_name(name)));
+        let json_path = Self::dir().join(format!("{}.json", file_name(name)));
+
+        if !toml_path.exists() && json_path.exists() { json_path } else { toml_path }
+    }
+
+    pub fn load(name: &str) -> io::Result<Recipe> {
+        Recipe::load(&Self::path_of(name))
+    }
+
+    /// Guarda (o sobrescribe) la receta en el almacén y devuelve su ruta.
+    pub fn save(recipe: &Recipe) -> io::Result<PathBuf> {
+        let path = Self::dir().join(format!("{}.toml", file_name(&recipe.name)));
+        recipe.save(&path)?;
+        Ok(path)
+    }
+
+    pub fn remove(name: &str) -> io::Result<()> {
+        fs::remove_file(Self::path_of(name))
+    }
+
+    /// Importa un archivo de receta externo al almacén.
+    pub fn import(path: &Path) -> io::Result<Recipe> {
+        let mut recipe = Recipe::load(path)?;
+        if recipe.name.trim().is_empty() {
+            recipe.name = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("receta")
+                .to_string();
+        }
+        Self::save(&recipe)?;
+        Ok(recipe)
+    }
+
+    /// Exporta una receta guardada a un archivo para compartirla.
+    pub fn export(name: &str, path: &Path) -> io::Result<()> {
+        Self::load(name)?.save(path)
+    }
+}
+
+/// Nombre de archivo seguro para una receta (mismo criterio que las carpetas de captura).
+fn file_name(name: &str) -> String {
+    name.trim().replace([' ', '/', '\\'], "_")
+}
\ No newline at end of file
@@ -1,16 +1,73 @@
 use serde::{Serialize, Deserialize};
+use nixvision_lib::filters::annotation::AnnotationStyle;
+use nixvision_lib::filters::lens::Intrinsics;
+use nixvision_lib::filters::scale::Scale;
 use std::fs;
-use std::path::{PathBuf};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct AppConfig {
     pub cameras: Vec<CameraSettings>, 
+    /// Hilos para barridos y lotes de frames (0 = todos los núcleos).
+    #[serde(default)]
+    pub threads: usize,
+    /// Colores, grosores y rótulos con los que se dibujan las detecciones.
+    #[serde(default)]
+    pub annotation: AnnotationStyle,
+    /// Imagen sobre la que se dibujan: `original`, `processed` o `side_by_side`.
+    #[serde(default)]
+    pub overlay: Overlay,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CameraSettings {
     pub name: String,
     pub url: String,
+    /// Escala calibrada (mm por píxel); sin ella las medidas se dan en píxeles.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mm_per_pixel: Option<f64>,
+    /// Intrínsecos y distorsión de lente (calibración con tablero de ajedrez).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub intrinsics: Option<Intrinsics>,
+}
+
+/// Sobre qué imagen dibuja las detecciones el paso de contornos.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Overlay {
+    /// El frame de entrada, en color (lo que se enseña al cliente).
+    #[default]
+    Original,
+    /// La imagen procesada que recibe el paso (bordes, máscara binaria...).
+    Processed,
+    /// Original y procesada lado a lado, anotadas las dos.
+    #[serde(alias = "side-by-side")]
+    SideBySide,
+}
+
+impl FromStr for Overlay {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "original" => Ok(Overlay::Original),
+            "processed" => Ok(Overlay::Processed),
+            "side_by_side" | "side-by-side" => Ok(Overlay::SideBySide),
+            other => Err(format!("superposición desconocida: '{}'", other)),
+        }
+    }
+}
+
+impl CameraSettings {
+    pub fn new(name: &str, url: &str) -> Self {
+        Self { name: name.to_string(), url: url.to_string(), mm_per_pixel: None, intrinsics: None }
+    }
+
+    /// Escala de la cámara, si está calibrada y es válida.
+    pub fn scale(&self) -> Option<Scale> {
+        self.mm_per_pixel.and_then(|v| Scale::new(v).ok())
+    }
 }
 
 impl AppConfig {
@@ -25,7 +82,7 @@ impl AppConfig {
         path
     }
 
-    fn get_config_dir() -> PathBuf {
+    pub(crate) fn get_config_dir() -> PathBuf {
         let mut path = std::env::var_os("HOME")
             .map(PathBuf::from)
             .expect("No se pudo encontrar la carpeta HOME");
@@ -39,10 +96,7 @@ impl AppConfig {
         
         if path.exists() {
             let content = fs::read_to_string(&path).unwrap_or_default();
-            toml::from_str(&content).unwrap_or_else(|_| {
-                eprintln!("⚠️ Error al parsear TOML en {:?}, cargando default...", path);
-                Self::default()
-            })
+            toml::from_str(&content).unwrap_or_else(|e| Self::recover(&path, &content, e))
         } else {
             let config = Self::default();
             let _ = config.save().ok(); 
@@ -50,6 +104,35 @@ impl AppConfig {
         }
     }
 
+    /// Configuración con errores: las secciones opcionales inválidas (`annotation`, `overlay`)
+    /// se descartan para no perder las cámaras ni sus calibraciones. Si aun así no se puede
+    /// leer, el archivo se aparta a `settings.toml.bak` antes de volver a los valores por defecto,
+    /// para que el próximo `save` no lo sobrescriba.
+    fn recover(path: &Path, content: &str, error: toml::de::Error) -> Self {
+        eprintln!("⚠️ Error al parsear TOML en {:?}: {}", path, error);
+
+        if let Ok(mut table) = toml::from_str::<toml::Table>(content) {
+            if table.get("annotation").is_some_and(|v| v.clone().try_into::<AnnotationStyle>().is_err()) {
+                eprintln!("⚠️ Sección [annotation] inválida: se usa el estilo por defecto.");
+                table.remove("annotation");
+            }
+            if table.get("overlay").is_some_and(|v| v.clone().try_into::<Overlay>().is_err()) {
+                eprintln!("⚠️ 'overlay' inválido: se usa 'original'.");
+                table.remove("overlay");
+            }
+            if let Ok(config) = toml::Value::Table(table).try_into() {
+                return config;
+            }
+        }
+
+        let backup = path.with_extension("toml.bak");
+        match fs::rename(path, &backup) {
+            Ok(()) => eprintln!("⚠️ Configuración apartada en {:?}; cargando default...", backup),
+            Err(e) => eprintln!("⚠️ No se pudo apartar {:?} ({}); cargando default...", path, e),
+        }
+        Self::default()
+    }
+
     /// Guarda los cambios de forma persistente
     pub fn save(&self) -> std::io::Result<()> {
         let dir = Self::get_config_dir();
@@ -81,12 +164,16 @@ impl AppConfig {
         self.cameras.len() < initial_len
     }
 
+    pub fn camera(&self, name: &str) -> Option<&CameraSettings> {
+        self.cameras.iter().find(|c| c.name == name)
+    }
+
     pub fn default() -> Self {
         Self {
-            cameras: vec![CameraSettings {
-                name: "Webcam Local".to_string(),
-                url: "0".to_string(),
-            }],
+            cameras: vec![CameraSettings::new("Webcam Local", "0")],
+            threads: 0,
+            annotation: AnnotationStyle::default(),
+            overlay: Overlay::default(),
         }
     }
 }
\ No newline at end of file
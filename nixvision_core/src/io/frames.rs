@@ -20,6 +20,15 @@ impl FrameCapture{
         imgcodecs::imwrite(filename, img, &opencv::core::Vector::new())
     }
 
+    /// Como `save_image`, pero un `imwrite` fallido (extensión desconocida, carpeta sin
+    /// permisos...) es un error en lugar de un `false` que se puede ignorar.
+    pub fn write_image(img: &Mat, filename: &str) -> Result<()> {
+        if !Self::save_image(img, filename)? {
+            return Err(opencv::Error::new(opencv::core::StsError, format!("No se pudo escribir '{}'", filename)));
+        }
+        Ok(())
+    }
+
     pub fn capture_from_stream(url: &str) -> opencv::Result<Mat> {
         use opencv::videoio::{VideoCapture, CAP_ANY, VideoCaptureTrait};
         
@@ -1 +1,2 @@
-pub mod frames;
\ No newline at end of file
+pub mod frames;
+pub mod report;
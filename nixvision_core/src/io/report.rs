@@ -0,0 +1,352 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use nixvision_lib::filters::scale::Scale;
+use crate::pipelines::scoring::RankedCandidate;
+use crate::pipelines::sweep::SweepResult;
+
+/// Una fila del informe: un intento del Auto-Tune con sus parámetros y métricas.
+#[derive(Serialize, Debug, Clone)]
+pub struct AttemptRecord {
+    /// Número de intento, empezando en 1 (como se muestra en consola).
+    pub attempt: usize,
+    pub label: String,
+    /// Parámetros variados, con clave `paso.parametro` (ej. `3.low`).
+    pub params: BTreeMap<String, f64>,
+    pub detected: bool,
+    pub objects: usize,
+    pub area: Option<f64>,
+    /// Área menos agujeros y número de agujeros (contornos en modo `tree`).
+    pub net_area: Option<f64>,
+    pub holes: Option<usize>,
+    pub perimeter: Option<f64>,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub solidity: Option<f64>,
+    pub circularity: Option<f64>,
+    pub aspect_ratio: Option<f64>,
+    pub extent: Option<f64>,
+    pub equivalent_diameter: Option<f64>,
+    pub orientation: Option<f64>,
+    pub centroid_x: Option<f32>,
+    pub centroid_y: Option<f32>,
+    /// Medidas en mm (solo con escala calibrada).
+    pub area_mm2: Option<f64>,
+    pub perimeter_mm: Option<f64>,
+    pub width_mm: Option<f64>,
+    pub height_mm: Option<f64>,
+    /// Posición en la clasificación (1 = mejor) y puntuación, si hubo detección.
+    pub rank: Option<usize>,
+    pub score: Option<f64>,
+    /// Tiempo de los pasos ejecutados; los que salieron de la caché de prefijos
+    /// (`cached_steps`) no cuentan, así que depende del orden del barrido.
+    pub elapsed_ms: f64,
+    pub cached_steps: usize,
+    /// Imagen guardada (solo las `top_n` mejores).
+    pub image: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Documento JSON completo.
+#[derive(Serialize, Debug)]
+struct ReportDocument<'a> {
+    sweep: &'a str,
+    mm_per_pixel: Option<f64>,
+    attempts: usize,
+    detected: usize,
+    results: &'a [AttemptRecord],
+}
+
+/// Informe persistente de un Auto-Tune en CSV, JSON y HTML (hoja de contactos).
+pub struct SweepReport {
+    pub name: String,
+    /// Escala con la que se calcularon las columnas en mm.
+    pub scale: Option<Scale>,
+    pub records: Vec<AttemptRecord>,
+}
+
+impl SweepReport {
+    /// Combina los resultados del barrido con su clasificación y las imágenes guardadas
+    /// (`saved[i]` corresponde a `ranking[i]`). Con `scale` se añaden las medidas en mm.
+    pub fn new(name: &str, results: &[SweepResult], ranking: &[RankedCandidate], saved: &[String], scale: Option<Scale>) -> Self {
+        let records = results
+            .iter()
+            .enumerate()
+            .map(|(i, result)| {
+                let position = ranking.iter().position(|r| r.result == i);
+                let primary = result.primary.as_ref();
+                let physical = primary.zip(scale).map(|(m, s)| s.measure(m));
+
+                AttemptRecord {
+                    attempt: result.candidate.index + 1,
+                    label: result.candidate.label(),
+                    params: result
+                        .candidate
+                        .assignments
+                        .iter()
+                        .map(|a| (format!("{}.{}", a.step, a.param), a.value))
+                        .collect(),
+                    detected: result.detected(),
+                    objects: result.objects,
+                    area: primary.map(|m| m.area),
+                    net_area: primary.map(|m| m.net_area),
+                    holes: primary.map(|m| m.holes),
+                    perimeter: primary.map(|m| m.perimeter),
+                    width: primary.map(|m| m.width),
+                    height: primary.map(|m| m.height),
+                    solidity: primary.map(|m| m.solidity),
+                    circularity: primary.map(|m| m.circularity),
+                    aspect_ratio: primary.map(|m| m.aspect_ratio),
+                    extent: primary.map(|m| m.extent),
+                    equivalent_diameter: primary.map(|m| m.equivalent_diameter),
+                    orientation: primary.map(|m| m.orientation),
+                    centroid_x: primary.map(|m| m.centroid.x),
+                    centroid_y: primary.map(|m| m.centroid.y),
+                    area_mm2: physical.map(|p| p.area_mm2),
+                    perimeter_mm: physical.map(|p| p.perimeter_mm),
+                    width_mm: physical.map(|p| p.width_mm),
+                    height_mm: physical.map(|p| p.height_mm),
+                    rank: position.map(|p| p + 1),
+                    score: position.map(|p| ranking[p].score.total),
+                    elapsed_ms: result.elapsed.as_secs_f64() * 1000.0,
+                    cached_steps: result.cached_steps,
+                    image: position.and_then(|p| saved.get(p).cloned()),
+                    error: result.error.clone(),
+                }
+            })
+            .collect();
+
+        Self { name: name.to_string(), scale, records }
+    }
+
+    /// Columnas de parámetros, en el orden en que aparecen en el barrido.
+    fn param_columns(&self) -> Vec<String> {
+        let mut columns: Vec<String> = Vec::new();
+        for record in &self.records {
+            for key in record.params.keys() {
+                if !columns.contains(key) {
+                    columns.push(key.clone());
+                }
+            }
+        }
+        columns
+    }
+
+    /// Un intento por fila; las métricas vacías quedan como celdas vacías.
+    pub fn write_csv(&self, path: &Path) -> io::Result<()> {
+        let params = self.param_columns();
+        let mut csv = String::from("attempt,label");
+        for column in &params {
+            csv.push(',');
+            csv.push_str(column);
+        }
+        csv.push_str(",detected,objects,area,net_area,holes,perimeter,width,height,solidity,circularity,aspect_ratio,extent,");
+        csv.push_str("equivalent_diameter,orientation,centroid_x,centroid_y,area_mm2,perimeter_mm,width_mm,height_mm,");
+        csv.push_str("rank,score,elapsed_ms,cached_steps,image,error\n");
+
+        for r in &self.records {
+            let mut row = vec![r.attempt.to_string(), csv_field(&r.label)];
+            row.extend(params.iter().map(|c| r.params.get(c).map(f64::to_string).unwrap_or_default()));
+            row.extend([
+                r.detected.to_string(),
+                r.objects.to_string(),
+                opt(r.area.map(|v| format!("{:.2}", v))),
+                opt(r.net_area.map(|v| format!("{:.2}", v))),
+                opt(r.holes),
+                opt(r.perimeter.map(|v| format!("{:.2}", v))),
+                opt(r.width),
+                opt(r.height),
+                opt(r.solidity.map(|v| format!("{:.4}", v))),
+                opt(r.circularity.map(|v| format!("{:.4}", v))),
+                opt(r.aspect_ratio.map(|v| format!("{:.4}", v))),
+                opt(r.extent.map(|v| format!("{:.4}", v))),
+                opt(r.equivalent_diameter.map(|v| format!("{:.2}", v))),
+                opt(r.orientation.map(|v| format!("{:.2}", v))),
+                opt(r.centroid_x.map(|v| format!("{:.1}", v))),
+                opt(r.centroid_y.map(|v| format!("{:.1}", v))),
+                opt(r.area_mm2.map(|v| format!("{:.3}", v))),
+                opt(r.perimeter_mm.map(|v| format!("{:.3}", v))),
+                opt(r.width_mm.map(|v| format!("{:.3}", v))),
+                opt(r.height_mm.map(|v| format!("{:.3}", v))),
+                opt(r.rank),
+                opt(r.score.map(|v| format!("{:.4}", v))),
+                format!("{:.3}", r.elapsed_ms),
+                r.cached_steps.to_string(),
+                csv_field(r.image.as_deref().unwrap_or("")),
+                csv_field(r.error.as_deref().unwrap_or("")),
+            ]);
+            csv.push_str(&row.join(","));
+            csv.push('\n');
+        }
+        fs::write(path, csv)
+    }
+
+    pub fn write_json(&self, path: &Path) -> io::Result<()> {
+        let document = ReportDocument {
+            sweep: &self.name,
+            mm_per_pixel: self.scale.map(|s| s.mm_per_pixel),
+            attempts: self.records.len(),
+            detected: self.records.iter().filter(|r| r.detected).count(),
+            results: &self.records,
+        };
+        let json = serde_json::to_string_pretty(&document)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(path, json)
+    }
+
+    /// Hoja de contactos: miniaturas de las detecciones guardadas y una tabla de todos los
+    /// intentos con detección, ordenable haciendo clic en cada columna.
+    /// Las rutas de las imágenes se escriben relativas a la carpeta del HTML.
+    pub fn write_html(&self, path: &Path) -> io::Result<()> {
+        let base = path.parent().unwrap_or(Path::new(""));
+        let params = self.param_columns();
+        let src = |image: &str| {
+            let image = Path::new(image);
+            escape(&image.strip_prefix(base).unwrap_or(image).to_string_lossy())
+        };
+
+        let mut html = String::new();
+        html.push_str("<!DOCTYPE html>\n<html lang=\"es\">\n<head>\n<meta charset=\"utf-8\">\n");
+        html.push_str(&format!("<title>NixVision Auto-Tune — {}</title>\n", escape(&self.name)));
+        html.push_str(STYLE);
+        html.push_str("</head>\n<body>\n");
+
+        let detected: Vec<&AttemptRecord> = self.records.iter().filter(|r| r.detected).collect();
+        html.push_str(&format!(
+            "<h1>Auto-Tune: {}</h1>\n<p>{} intentos, {} con detección.</p>\n",
+            escape(&self.name), self.records.len(), detected.len()
+        ));
+
+        // 1. Miniaturas de las mejores combinaciones
+        let mut best: Vec<&AttemptRecord> = detected.iter().copied().filter(|r| r.image.is_some()).collect();
+        best.sort_by_key(|r| r.rank);
+        html.push_str("<div class=\"sheet\">\n");
+        for r in &best {
+            let image = r.image.as_deref().unwrap_or("");
+            let size = match (r.area_mm2, r.width_mm, r.height_mm) {
+                (Some(area), Some(w), Some(h)) => format!("área {:.1} mm² · {:.1}x{:.1} mm", area, w, h),
+                _ => format!("área {:.0} px · {}x{}", r.area.unwrap_or(0.0), r.width.unwrap_or(0), r.height.unwrap_or(0)),
+            };
+            html.push_str(&format!(
+                "<figure><a href=\"{0}\"><img src=\"{0}\" loading=\"lazy\"></a><figcaption><b>#{1}</b> puntuación {2:.3}<br>{3}<br>{4}</figcaption></figure>\n",
+                src(image),
+                r.rank.unwrap_or(0),
+                r.score.unwrap_or(0.0),
+                escape(&r.label),
+                size,
+            ));
+        }
+        html.push_str("</div>\n");
+
+        // 2. Tabla ordenable
+        html.push_str("<table id=\"results\">\n<thead><tr><th>imagen</th><th>#</th><th>intento</th>");
+        for column in &params {
+            html.push_str(&format!("<th>{}</th>", escape(column)));
+        }
+        html.push_str("<th>puntuación</th><th>área</th><th>perímetro</th><th>ancho</th><th>alto</th><th>solidez</th><th>circularidad</th><th>aspecto</th><th>extensión</th><th>diámetro eq.</th><th>orientación</th>");
+        if self.scale.is_some() {
+            html.push_str("<th>área mm²</th><th>perímetro mm</th><th>ancho mm</th><th>alto mm</th>");
+        }
+        html.push_str("<th>objetos</th><th>ms</th><th>pasos en caché</th></tr></thead>\n<tbody>\n");
+
+        for r in &detected {
+            let thumb = match &r.image {
+                Some(image) => format!("<img class=\"thumb\" src=\"{}\" loading=\"lazy\">", src(image)),
+                None => String::new(),
+            };
+            html.push_str(&format!("<tr><td>{}</td><td>{}</td><td>{}</td>", thumb, opt(r.rank), r.attempt));
+            for column in &params {
+                html.push_str(&format!("<td>{}</td>", r.params.get(column).map(f64::to_string).unwrap_or_default()));
+            }
+            html.push_str(&format!(
+                "<td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td>",
+                opt(r.score.map(|v| format!("{:.3}", v))),
+                opt(r.area.map(|v| format!("{:.1}", v))),
+                opt(r.perimeter.map(|v| format!("{:.1}", v))),
+                opt(r.width),
+                opt(r.height),
+                opt(r.solidity.map(|v| format!("{:.3}", v))),
+                opt(r.circularity.map(|v| format!("{:.3}", v))),
+                opt(r.aspect_ratio.map(|v| format!("{:.3}", v))),
+                opt(r.extent.map(|v| format!("{:.3}", v))),
+                opt(r.equivalent_diameter.map(|v| format!("{:.1}", v))),
+                opt(r.orientation.map(|v| format!("{:.1}", v))),
+            ));
+            if self.scale.is_some() {
+                html.push_str(&format!(
+                    "<td>{}</td><td>{}</td><td>{}</td><td>{}</td>",
+                    opt(r.area_mm2.map(|v| format!("{:.2}", v))),
+                    opt(r.perimeter_mm.map(|v| format!("{:.2}", v))),
+                    opt(r.width_mm.map(|v| format!("{:.2}", v))),
+                    opt(r.height_mm.map(|v| format!("{:.2}", v))),
+                ));
+            }
+            html.push_str(&format!("<td>{}</td><td>{:.2}</td><td>{}</td></tr>\n", r.objects, r.elapsed_ms, r.cached_steps));
+        }
+        html.push_str("</tbody>\n</table>\n");
+        html.push_str(SCRIPT);
+        html.push_str("</body>\n</html>\n");
+
+        fs::write(path, html)
+    }
+}
+
+fn opt<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+/// Entrecomilla el campo si contiene separadores o comillas.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+const STYLE: &str = "<style>
+body { font-family: sans-serif; margin: 2em; color: #222; }
+.sheet { display: flex; flex-wrap: wrap; gap: 1em; margin-bottom: 2em; }
+figure { margin: 0; width: 240px; }
+figure img { width: 240px; border: 1px solid #ccc; }
+figcaption { font-size: 0.85em; word-break: break-all; }
+table { border-collapse: collapse; font-size: 0.9em; }
+th, td { border: 1px solid #ddd; padding: 4px 8px; text-align: right; }
+th { background: #f0f0f0; cursor: pointer; user-select: none; }
+th.asc::after { content: ' ▲'; }
+th.desc::after { content: ' ▼'; }
+img.thumb { height: 48px; }
+</style>
+";
+
+/// Ordena la tabla al hacer clic en una cabecera (numérico si ambos valores lo son; vacíos al final).
+const SCRIPT: &str = "<script>
+document.querySelectorAll('#results th').forEach((th, col) => {
+  th.addEventListener('click', () => {
+    const body = th.closest('table').tBodies[0];
+    const asc = !th.classList.contains('asc');
+    th.parentNode.querySelectorAll('th').forEach(h => h.classList.remove('asc', 'desc'));
+    th.classList.add(asc ? 'asc' : 'desc');
+    const rows = Array.from(body.rows);
+    rows.sort((a, b) => {
+      const x = a.cells[col].textContent, y = b.cells[col].textContent;
+      if (x === '' || y === '') return (x === '') - (y === '');
+      const nx = parseFloat(x), ny = parseFloat(y);
+      const cmp = isNaN(nx) || isNaN(ny) ? x.localeCompare(y) : nx - ny;
+      return asc ? cmp : -cmp;
+    });
+    rows.forEach(r => body.appendChild(r));
+  });
+});
+</script>
+";
@@ -1,18 +1,28 @@
 mod ui;
+mod cli;
 mod commands;
 mod io;
 mod config;
 mod pipelines;
+use std::process::ExitCode;
 use crate::ui::Interface;
+use crate::cli::Cli;
 use crate::commands::CommandManager;
 
-fn main() -> opencv::Result<()> {
+fn main() -> ExitCode {
+    // Con argumentos se ejecuta en modo headless (scripts, cron, CI)
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        return Cli::run(&args);
+    }
+
     // FORMA CORRECTA: Llamada estática (sin instancia)
     Interface::welcome_banner(); 
 
     if let Err(e) = CommandManager::run_interactive_menu() {
         Interface::error(&format!("Error: {}", e));
+        return ExitCode::FAILURE;
     }
 
-    Ok(())
+    ExitCode::SUCCESS
 }
\ No newline at end of file
@@ -0,0 +1,123 @@
+use opencv::{core::Mat, prelude::*};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
+use crate::pipelines::step::PipelineStep;
+
+/// Memoria de resultados intermedios indexada por el prefijo de la receta.
+///
+/// En un barrido, `grayscale -> gaussian_blur(k=5)` es idéntico para todas las
+/// combinaciones que solo cambian Canny o el área mínima: se calcula una vez y el resto
+/// parte de la imagen guardada. Una caché solo es válida para **una** imagen de entrada.
+/// Es segura entre hilos; si dos hilos calculan el mismo prefijo a la vez, gana el primero.
+/// La memoria está acotada en bytes: al llenarse se descartan las imágenes usadas hace más tiempo.
+pub struct PrefixCache {
+    state: Mutex<CacheState>,
+    /// Bytes máximos de imagen guardados.
+    max_bytes: usize,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+#[derive(Default)]
+struct CacheState {
+    entries: HashMap<String, CacheEntry>,
+    bytes: usize,
+    /// Reloj lógico para saber qué entrada se usó hace más tiempo.
+    clock: u64,
+}
+
+struct CacheEntry {
+    mat: Mat,
+    /// Advertencias que produjeron los pasos del prefijo, para repetirlas al reutilizarlo.
+    warnings: Vec<String>,
+    bytes: usize,
+    last_used: u64,
+}
+
+impl PrefixCache {
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            state: Mutex::new(CacheState::default()),
+            max_bytes,
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    /// Clave del prefijo: los pasos con todos sus parámetros.
+    pub fn key(prefix: &[PipelineStep]) -> String {
+        format!("{:?}", prefix)
+    }
+
+    /// Solo se cachean los pasos que transforman la imagen; a partir del paso de
+    /// contornos el resultado incluye objetos medidos y ya no se reutiliza.
+    pub fn cacheable(step: &PipelineStep) -> bool {
+        !matches!(step, PipelineStep::Contours { .. })
+    }
+
+    /// Prefijo cacheado más largo de `receta`: devuelve cuántos pasos cubre, su imagen y
+    /// las advertencias de esos pasos.
+    pub fn longest(&self, receta: &[PipelineStep]) -> Option<(usize, Mat, Vec<String>)> {
+        let cacheable = receta.iter().take_while(|s| Self::cacheable(s)).count();
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.clock += 1;
+        let clock = state.clock;
+
+        let found = (1..=cacheable).rev().find_map(|len| {
+            let entry = state.entries.get_mut(&Self::key(&receta[..len]))?;
+            entry.last_used = clock;
+            Some((len, entry.mat.clone(), entry.warnings.clone()))
+        });
+
+        match found {
+            Some(_) => self.hits.fetch_add(1, Ordering::Relaxed),
+            None => self.misses.fetch_add(1, Ordering::Relaxed),
+        };
+        found
+    }
+
+    /// Guarda el resultado de aplicar `prefix` a la imagen de entrada (con las advertencias
+    /// de sus pasos), descartando las
+    /// entradas menos usadas recientemente hasta que quepa. Una imagen mayor que todo el
+    /// presupuesto no se guarda.
+    pub fn insert(&self, prefix: &[PipelineStep], mat: &Mat, warnings: &[String]) {
+        let bytes = mat.total() * mat.elem_size().unwrap_or(0);
+        if bytes > self.max_bytes {
+            return;
+        }
+
+        let key = Self::key(prefix);
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        if state.entries.contains_key(&key) {
+            return;
+        }
+        while state.bytes + bytes > self.max_bytes {
+            let Some(oldest) = state.entries.iter().min_by_key(|(_, e)| e.last_used).map(|(k, _)| k.clone()) else {
+                break;
+            };
+            if let Some(evicted) = state.entries.remove(&oldest) {
+                state.bytes -= evicted.bytes;
+            }
+        }
+
+        state.clock += 1;
+        let last_used = state.clock;
+        state.bytes += bytes;
+        state.entries.insert(key, CacheEntry { mat: mat.clone(), warnings: warnings.to_vec(), bytes, last_used });
+    }
+
+    /// Ejecuciones que reutilizaron algún prefijo.
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    pub fn misses(&self) -> usize {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// Imágenes guardadas actualmente.
+    pub fn stored(&self) -> usize {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner).entries.len()
+    }
+}
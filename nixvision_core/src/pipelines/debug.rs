@@ -0,0 +1,128 @@
+use opencv::{
+    core::{self, Mat, Point, Scalar, Size, Vector},
+    imgproc,
+    prelude::*,
+    Result,
+};
+use std::path::{Path, PathBuf};
+use crate::io::frames::FrameCapture;
+
+/// Alto (px) de cada etapa dentro del montaje.
+const TILE_HEIGHT: i32 = 240;
+/// Etapas por fila del montaje.
+const TILES_PER_ROW: usize = 4;
+
+/// Volcado etapa por etapa de las imágenes intermedias del pipeline.
+/// Cada etapa se guarda como `{índice}_{paso_y_parámetros}.png`, por ejemplo
+/// `03_canny_low50_high150.png`; con `montage` se genera además `montage.png`.
+pub struct DebugDump {
+    dir: PathBuf,
+    montage: bool,
+    tiles: Vec<Mat>,
+}
+
+impl DebugDump {
+    pub fn new(dir: &Path, montage: bool) -> Result<Self> {
+        std::fs::create_dir_all(dir).map_err(|e| {
+            opencv::Error::new(core::StsError, format!("No se pudo crear la carpeta de depuración {:?}: {}", dir, e))
+        })?;
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            montage,
+            tiles: Vec::new(),
+        })
+    }
+
+    /// Guarda una etapa (el índice 00 es la imagen de entrada).
+    pub fn record(&mut self, index: usize, label: &str, img: &Mat) -> Result<()> {
+        let path = self.dir.join(format!("{:02}_{}.png", index, label));
+        FrameCapture::write_image(img, &path.to_string_lossy())?;
+
+        if self.montage {
+            let tile = Self::tile(img, &format!("{:02} {}", index, label))?;
+            self.tiles.push(tile);
+        }
+        Ok(())
+    }
+
+    /// Escribe el montaje (si se pidió) y devuelve su ruta.
+    pub fn finish(self) -> Result<Option<PathBuf>> {
+        if !self.montage || self.tiles.is_empty() {
+            return Ok(None);
+        }
+
+        let tile_width = self.tiles.iter().map(|t| t.cols()).max().unwrap_or(0);
+        let mut rows = Vector::<Mat>::new();
+
+        for chunk in self.tiles.chunks(TILES_PER_ROW) {
+            let mut row = Vector::<Mat>::new();
+            for i in 0..TILES_PER_ROW {
+                // Rellenamos con negro para que todas las filas midan lo mismo
+                let tile = match chunk.get(i) {
+                    Some(t) => Self::pad(t, tile_width)?,
+                    None => Mat::new_rows_cols_with_default(TILE_HEIGHT, tile_width, core::CV_8UC3, Scalar::all(0.0))?,
+                };
+                row.push(tile);
+            }
+            let mut joined = Mat::default();
+            core::hconcat(&row, &mut joined)?;
+            rows.push(joined);
+        }
+
+        let mut montage = Mat::default();
+        core::vconcat(&rows, &mut montage)?;
+
+        let path = self.dir.join("montage.png");
+        FrameCapture::write_image(&montage, &path.to_string_lossy())?;
+        Ok(Some(path))
+    }
+
+    /// Lleva cualquier etapa (gris, 16 bits, HSV...) a BGR de 8 bits, alto fijo y con su etiqueta.
+    fn tile(img: &Mat, label: &str) -> Result<Mat> {
+        let mut src8 = Mat::default();
+        if img.depth() != core::CV_8U {
+            core::convert_scale_abs(img, &mut src8, 1.0, 0.0)?;
+        } else {
+            src8 = img.clone();
+        }
+
+        let mut bgr = Mat::default();
+        match src8.channels() {
+            1 => imgproc::cvt_color_def(&src8, &mut bgr, imgproc::COLOR_GRAY2BGR)?,
+            4 => imgproc::cvt_color_def(&src8, &mut bgr, imgproc::COLOR_BGRA2BGR)?,
+            _ => bgr = src8,
+        }
+
+        let size = bgr.size()?;
+        if size.height == 0 || size.width == 0 {
+            return Mat::new_rows_cols_with_default(TILE_HEIGHT, TILE_HEIGHT, core::CV_8UC3, Scalar::all(0.0));
+        }
+        let width = ((size.width as f64 * TILE_HEIGHT as f64 / size.height as f64).round() as i32).max(1);
+
+        let mut tile = Mat::default();
+        imgproc::resize(&bgr, &mut tile, Size::new(width, TILE_HEIGHT), 0.0, 0.0, imgproc::INTER_AREA)?;
+        imgproc::put_text(
+            &mut tile,
+            label,
+            Point::new(8, 20),
+            imgproc::FONT_HERSHEY_SIMPLEX,
+            0.5,
+            Scalar::new(0.0, 255.0, 255.0, 0.0), // Amarillo
+            1,
+            imgproc::LINE_AA,
+            false,
+        )?;
+        Ok(tile)
+    }
+
+    fn pad(tile: &Mat, width: i32) -> Result<Mat> {
+        let extra = width - tile.cols();
+        if extra <= 0 {
+            return Ok(tile.clone());
+        }
+        let mut padded = Mat::default();
+        core::copy_make_border(tile, &mut padded, 0, 0, 0, extra, core::BORDER_CONSTANT, Scalar::all(0.0))?;
+        Ok(padded)
+    }
+}
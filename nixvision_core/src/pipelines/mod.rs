@@ -1 +1,9 @@
-pub mod processing;
\ No newline at end of file
+pub mod processing;
+pub mod step;
+pub mod debug;
+pub mod profiler;
+pub mod validation;
+pub mod sweep;
+pub mod scoring;
+pub mod parallel;
+pub mod cache;
@@ -0,0 +1,61 @@
+use opencv::{core::{self, Mat}, Result};
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::sync::{Mutex, PoisonError};
+
+use crate::pipelines::processing::{DynamicPipeline, PipelineOptions, ProcessResult};
+use crate::pipelines::step::PipelineStep;
+
+/// Pool de trabajo para barridos y lotes; `threads = 0` usa todos los núcleos.
+pub fn worker_pool(threads: usize) -> Result<ThreadPool> {
+    ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .thread_name(|i| format!("nixvision-worker-{}", i))
+        .build()
+        .map_err(|e| opencv::Error::new(core::StsError, format!("No se pudo crear el pool de hilos: {}", e)))
+}
+
+/// Contador de trabajos terminados compartido entre hilos.
+pub struct Progress {
+    total: usize,
+    done: Mutex<usize>,
+}
+
+impl Progress {
+    pub fn new(total: usize) -> Self {
+        Self { total, done: Mutex::new(0) }
+    }
+
+    /// Marca un trabajo como terminado y llama a `report(hechos, total)` con el candado tomado,
+    /// así las líneas de distintos hilos no se mezclan y el contador nunca retrocede.
+    pub fn tick(&self, report: impl FnOnce(usize, usize)) {
+        let mut done = self.done.lock().unwrap_or_else(PoisonError::into_inner);
+        *done += 1;
+        report(*done, self.total);
+    }
+}
+
+/// Aplica la receta a cada frame en paralelo. `on_frame(i, resultado)` se llama desde los
+/// hilos de trabajo en el orden en que terminan; el vector devuelto respeta el orden de `frames`.
+pub fn run_batch<T, F>(
+    frames: &[Mat],
+    receta: &[PipelineStep],
+    options: &PipelineOptions,
+    threads: usize,
+    on_frame: F,
+) -> Result<Vec<T>>
+where
+    T: Send,
+    F: Fn(usize, &ProcessResult) -> Result<T> + Sync,
+{
+    worker_pool(threads)?.install(|| {
+        frames
+            .par_iter()
+            .enumerate()
+            .map(|(i, frame)| {
+                let res = DynamicPipeline::run_with(frame, receta, options)?;
+                on_frame(i, &res)
+            })
+            .collect()
+    })
+}
@@ -1,184 +1,377 @@
 use nixvision_lib::filters::{
-    affine::NixAffine, color::ColorConverter, contour::NixContour,
-    edges::EdgeManager, illumination::IlluminationManager, noise::NoiseReducer
+    affine::NixAffine, annotation::{AnnotationStyle, Annotator}, color::ColorConverter, contour::{ContourMetrics, NixContour},
+    lens::{Intrinsics, LensCalibration}, scale::{PhysicalMetrics, Scale},
+    edges::EdgeManager, illumination::IlluminationManager, morphology::Morphology, noise::NoiseReducer,
+    threshold::{AdaptiveMethod, Thresholder},
 };
 use opencv::{prelude::*, core,imgproc, Result};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use crate::config::settings::{AppConfig, Overlay};
+use crate::pipelines::cache::PrefixCache;
+use crate::pipelines::debug::DebugDump;
+use crate::pipelines::step::PipelineStep;
 
+/// Metadatos de un paso ya ejecutado.
+#[derive(Debug, Clone)]
+pub struct StepReport {
+    pub index: usize,
+    pub step: PipelineStep,
+    pub width: i32,
+    pub height: i32,
+    pub channels: i32,
+    /// Contornos medidos en este paso (0 si no es un paso de contornos).
+    pub objects: usize,
+    /// Tiempo de pared del paso (sin contar el volcado de depuración).
+    pub elapsed: Duration,
+    /// El resultado salió de la caché de prefijos (no se ejecutó; `elapsed` es 0).
+    pub cached: bool,
+}
+
+/// Objeto detectado por un paso de contornos.
+#[derive(Debug, Clone)]
+pub struct DetectedObject {
+    /// Identificador dentro de la ejecución (1, 2, 3...), el mismo que se rotula en la imagen.
+    pub id: usize,
+    /// Índice (base 0) del paso de contornos que lo detectó.
+    pub step: usize,
+    pub metrics: ContourMetrics,
+    /// Medidas en mm, si la ejecución tenía escala calibrada.
+    pub physical: Option<PhysicalMetrics>,
+}
+
+/// Resultado completo de una ejecución del pipeline.
 pub struct ProcessResult {
-    pub image: opencv::core::Mat,
-    pub area: f64,
-    pub perimeter: f64, 
-    pub width: i32,     
-    pub height: i32,    
-    pub detected: bool,
+    /// Imagen final: con paso de contornos, las detecciones dibujadas según `overlay`
+    /// (aunque haya pasos después); si no, la salida del último paso.
+    pub image: Mat,
+    /// Todos los objetos seleccionados por los pasos de contornos, en orden de ID
+    /// (dentro de cada paso, el principal primero).
+    pub objects: Vec<DetectedObject>,
+    pub steps: Vec<StepReport>,
+    pub warnings: Vec<String>,
+    /// Suma de los tiempos de todos los pasos (tiempo de cómputo del frame).
+    pub elapsed: Duration,
 }
+
+impl ProcessResult {
+    pub fn detected(&self) -> bool {
+        !self.objects.is_empty()
+    }
+
+    /// Número de objetos detectados (piezas, frutas, componentes...).
+    pub fn count(&self) -> usize {
+        self.objects.len()
+    }
+
+    /// Objeto principal según la selección del paso de contornos (el primero rotulado).
+    pub fn primary(&self) -> Option<&ContourMetrics> {
+        self.objects.first().map(|o| &o.metrics)
+    }
+
+    /// Objeto con el identificador `id`.
+    pub fn object(&self, id: usize) -> Option<&DetectedObject> {
+        self.objects.iter().find(|o| o.id == id)
+    }
+}
+
+/// Detecciones dibujadas por el último paso de contornos.
+struct Annotated {
+    /// Frame original anotado (`None` si no coincide en tamaño con la imagen procesada).
+    original: Option<Mat>,
+    processed: Mat,
+    overlay: Overlay,
+}
+
+impl Annotated {
+    /// Imagen final según la superposición elegida.
+    fn image(&self) -> Result<Mat> {
+        match (self.overlay, &self.original) {
+            (Overlay::Original, Some(original)) => Ok(original.clone()),
+            (Overlay::SideBySide, Some(original)) => {
+                let mut both = Mat::default();
+                core::hconcat2(original, &self.processed, &mut both)?;
+                Ok(both)
+            }
+            _ => Ok(self.processed.clone()),
+        }
+    }
+}
+
+/// Opciones de ejecución del pipeline.
+#[derive(Debug, Clone, Default)]
+pub struct PipelineOptions {
+    /// Modo depuración: carpeta donde volcar la imagen intermedia de cada paso.
+    pub debug_dir: Option<PathBuf>,
+    /// Con `debug_dir`, genera también un montaje con todas las etapas lado a lado.
+    pub montage: bool,
+    /// Cómo se dibujan los objetos detectados en la imagen final.
+    pub annotation: AnnotationStyle,
+    /// Sobre qué imagen se dibujan.
+    pub overlay: Overlay,
+    /// Escala de la cámara: añade las medidas en mm a cada objeto.
+    pub scale: Option<Scale>,
+    /// Calibración de lente de la cámara, para el paso `undistort`.
+    pub intrinsics: Option<Intrinsics>,
+}
+
+impl PipelineOptions {
+    /// Estilo de anotación y superposición de la configuración global, sin depuración.
+    pub fn from_config(config: &AppConfig) -> Self {
+        Self {
+            annotation: config.annotation.clone(),
+            overlay: config.overlay,
+            ..Self::default()
+        }
+    }
+}
+
 pub struct DynamicPipeline;
 
 impl DynamicPipeline {
 
-    pub fn process_with_metadata(
-        img: &Mat, 
-        receta: &Vec<(i32, i32, f64, f64)>
-    ) -> Result<ProcessResult> {
-        let mut current_mat = img.clone();
-
-        for (step_type, option, p1, p2) in receta {
-            match *step_type {
-                6 => { // Paso de Contornos (Puntos 4-7)
-                    let mut display = Mat::default();
-                    if current_mat.channels() == 1 {
-                        imgproc::cvt_color_def(&current_mat, &mut display, imgproc::COLOR_GRAY2BGR)?;
-                    } else {
-                        display = current_mat.clone();
+    /// Motor único del pipeline: aplica la receta completa y devuelve la imagen final,
+    /// los objetos detectados, los metadatos de cada paso y las advertencias.
+    /// No imprime nada; la presentación queda en la capa de comandos/CLI.
+    pub fn run(img: &Mat, receta: &[PipelineStep]) -> Result<ProcessResult> {
+        Self::run_with(img, receta, &PipelineOptions::default())
+    }
+
+    /// Igual que `run`, con opciones de ejecución (volcado de etapas, montaje).
+    pub fn run_with(img: &Mat, receta: &[PipelineStep], options: &PipelineOptions) -> Result<ProcessResult> {
+        Self::execute(img, receta, options, None)
+    }
+
+    /// Igual que `run_with`, reutilizando el prefijo más largo de la receta ya calculado en
+    /// `cache` y guardando los nuevos. `cache` debe corresponder a esta misma `img`.
+    /// En modo depuración no se usa la caché (hay que volcar todas las etapas).
+    pub fn run_cached(img: &Mat, receta: &[PipelineStep], options: &PipelineOptions, cache: &PrefixCache) -> Result<ProcessResult> {
+        let cache = if options.debug_dir.is_some() { None } else { Some(cache) };
+        Self::execute(img, receta, options, cache)
+    }
+
+    fn execute(img: &Mat, receta: &[PipelineStep], options: &PipelineOptions, cache: Option<&PrefixCache>) -> Result<ProcessResult> {
+        let mut dump = match &options.debug_dir {
+            Some(dir) => Some(DebugDump::new(dir, options.montage)?),
+            None => None,
+        };
+        if let Some(dump) = dump.as_mut() {
+            dump.record(0, "input", img)?;
+        }
+
+        let mut objects = Vec::new();
+        let mut steps = Vec::with_capacity(receta.len());
+        let mut warnings = Vec::new();
+        // Las detecciones se dibujan aparte: los pasos siguientes siguen trabajando
+        // sobre la imagen procesada, no sobre una con cajas y rótulos
+        let mut annotated: Option<Annotated> = None;
+
+        // Prefijo ya calculado: esos pasos se reportan como cacheados (con el tamaño de la
+        // imagen guardada y sus advertencias) y no se ejecutan
+        let (skip, mut current_mat) = match cache.and_then(|c| c.longest(receta)) {
+            Some((len, mat, cached_warnings)) => {
+                warnings.extend(cached_warnings);
+                (len, mat)
+            }
+            None => (0, img.clone()),
+        };
+        // Imagen de fondo para dibujar las detecciones: el frame original, corregido
+        // también si la receta corrige la lente (aunque ese paso venga de la caché)
+        let mut base = img.clone();
+        if let Some(intrinsics) = &options.intrinsics
+            && receta[..skip].iter().any(|s| matches!(s, PipelineStep::Undistort))
+        {
+            base = LensCalibration::undistort(img, intrinsics)?;
+        }
+
+        if skip > 0 {
+            let size = current_mat.size()?;
+            steps.extend(receta[..skip].iter().enumerate().map(|(index, step)| StepReport {
+                index,
+                step: step.clone(),
+                width: size.width,
+                height: size.height,
+                channels: current_mat.channels(),
+                objects: 0,
+                elapsed: Duration::ZERO,
+                cached: true,
+            }));
+        }
+
+        for (index, step) in receta.iter().enumerate().skip(skip) {
+            let mut found = 0;
+            let started = Instant::now();
+
+            current_mat = match step {
+                PipelineStep::Undistort => match &options.intrinsics {
+                    Some(intrinsics) => {
+                        let corrected = LensCalibration::undistort(&current_mat, intrinsics)?;
+                        base = if index == 0 { corrected.clone() } else { LensCalibration::undistort(&base, intrinsics)? };
+                        corrected
+                    }
+                    None => {
+                        warnings.push(format!("Paso {} ({}): la cámara no tiene calibración de lente; se omite", index + 1, step));
+                        current_mat
+                    }
+                },
+                // EXTRACCIÓN DE CONTORNOS (Puntos 4-7 del procedimiento [cite: 21, 22])
+                PipelineStep::Contours { filter, mode, level, select } => {
+                    // Las coordenadas son las de la imagen procesada: si un paso afín la movió,
+                    // no coinciden con el frame original
+                    let mut overlay = options.overlay;
+                    if overlay != Overlay::Processed && receta[..index].iter().any(PipelineStep::is_affine) {
+                        warnings.push(format!(
+                            "Paso {} ({}): hay transformaciones afines antes; las detecciones pueden no coincidir con la imagen original",
+                            index + 1, step
+                        ));
+                    }
+                    let (base_size, size) = (base.size()?, current_mat.size()?);
+                    let fits = base_size == size;
+                    if overlay != Overlay::Processed && !fits {
+                        warnings.push(format!(
+                            "Paso {} ({}): la imagen procesada mide {}x{} y la original {}x{}; se dibuja sobre la procesada",
+                            index + 1, step, size.width, size.height, base_size.width, base_size.height
+                        ));
+                        overlay = Overlay::Processed;
                     }
 
-                    // Ejecutamos medición real con tus filtros
-                    let metrics = NixContour::find_and_measure(&current_mat, &mut display, *p1)?;
-
-                    if let Some(largest) = metrics.iter().max_by(|a, b| a.area.partial_cmp(&b.area).unwrap()) {
-                        NixContour::draw_highlight(&mut display, largest)?;
-                        
-                        // Retorno inmediato: Aquí las variables no necesitan ser mutables 
-                        // porque se pasan directamente al inicializador.
-                        return Ok(ProcessResult {
-                            image: display,
-                            area: largest.area,
-                            perimeter: largest.perimeter, 
-                            width: largest.width,         
-                            height: largest.height,       
-                            detected: true,
-                        });
+                    let candidates = NixContour::find_and_measure(&current_mat, filter, *mode, *level)?;
+                    let metrics = NixContour::select(candidates, *select, current_mat.size()?);
+
+                    if metrics.is_empty() {
+                        warnings.push(format!("Paso {} ({}): ningún contorno cumple el filtro", index + 1, step));
+                    }
+
+                    // Dibujamos y numeramos los objetos seleccionados (Punto 7 / Extensión [cite: 27]).
+                    // El original acumula los objetos de todos los pasos de contornos
+                    let mut original = match annotated.take() {
+                        Some(Annotated { original: Some(original), .. }) if fits => Some(original),
+                        _ if fits => Some(to_bgr(&base)?),
+                        _ => None,
+                    };
+                    let mut processed = to_bgr(&current_mat)?;
+                    found = metrics.len();
+                    for metrics in metrics {
+                        let id = objects.len() + 1;
+                        let scale = options.scale.as_ref();
+                        if let Some(original) = original.as_mut() {
+                            Annotator::draw(original, id, &metrics, scale, &options.annotation)?;
+                        }
+                        Annotator::draw(&mut processed, id, &metrics, scale, &options.annotation)?;
+                        let physical = scale.map(|s| s.measure(&metrics));
+                        objects.push(DetectedObject { id, step: index, metrics, physical });
                     }
+
+                    annotated = Some(Annotated { original, processed, overlay });
+                    current_mat
                 },
-                _ => {
-                    current_mat = Self::apply_step(&current_mat, (*step_type, *option, *p1, *p2))?;
+                _ => Self::apply_step(&current_mat, step)?,
+            };
+
+            let elapsed = started.elapsed();
+            let size = current_mat.size()?;
+            steps.push(StepReport {
+                index,
+                step: step.clone(),
+                width: size.width,
+                height: size.height,
+                channels: current_mat.channels(),
+                objects: found,
+                elapsed,
+                cached: false,
+            });
+
+            if let Some(cache) = cache
+                && receta[..=index].iter().all(PrefixCache::cacheable)
+            {
+                // Todas las advertencias hasta aquí son de pasos del prefijo
+                cache.insert(&receta[..=index], &current_mat, &warnings);
+            }
+
+            if let Some(dump) = dump.as_mut() {
+                match (&annotated, step) {
+                    (Some(annotated), PipelineStep::Contours { .. }) => dump.record(index + 1, &step.slug(), &annotated.image()?)?,
+                    _ => dump.record(index + 1, &step.slug(), &current_mat)?,
                 }
             }
         }
 
-        // Si llegamos aquí, no hubo detección. Retornamos valores por defecto (Punto 5)
-        Ok(ProcessResult {
-            image: current_mat,
-            area: 0.0,
-            perimeter: 0.0,
-            width: 0,
-            height: 0,
-            detected: false,
-        })
+        if let Some(dump) = dump {
+            dump.finish()?;
+        }
+
+        let image = match annotated {
+            Some(annotated) => annotated.image()?,
+            None => current_mat,
+        };
+        let elapsed = steps.iter().map(|s| s.elapsed).sum();
+        Ok(ProcessResult { image, objects, steps, warnings, elapsed })
     }
 
     /// Implementación modular de tus filtros
-    pub fn apply_step(img: &Mat, step: (i32, i32, f64, f64)) -> Result<Mat> {
-        let (step_type, option, p1, p2) = step;
-        match step_type {
-            1 => match option { // Color
-                1 => ColorConverter::to_grayscale(img),
-                2 => ColorConverter::to_hsv(img),
-                _ => Ok(img.clone()),
+    pub fn apply_step(img: &Mat, step: &PipelineStep) -> Result<Mat> {
+        match step {
+            // Color
+            PipelineStep::Grayscale => ColorConverter::to_grayscale(img),
+            PipelineStep::Hsv => ColorConverter::to_hsv(img),
+            PipelineStep::ValueChannel => ColorConverter::extract_v_channel(img),
+            // Iluminación
+            PipelineStep::Normalize => IlluminationManager::normalize(img),
+            PipelineStep::BackgroundCorrection => IlluminationManager::background_correction(img),
+            PipelineStep::Clahe { clip_limit, grid } => {
+                IlluminationManager::apply_clahe(img, *clip_limit, core::Size::new(*grid, *grid)).map_err(Into::into)
             },
-            2 => match option { // Iluminación
-                1 => IlluminationManager::normalize(img),
-                3 => IlluminationManager::apply_clahe(img, p1, core::Size::new(8, 8)),
-                _ => Ok(img.clone()),
+            // Ruido
+            PipelineStep::GaussianBlur { kernel } => NoiseReducer::gaussian(img, *kernel).map_err(Into::into),
+            PipelineStep::MedianBlur { kernel } => NoiseReducer::median(img, *kernel).map_err(Into::into),
+            PipelineStep::Bilateral { diameter, sigma_color, sigma_space } => {
+                NoiseReducer::bilateral(img, *diameter, *sigma_color, *sigma_space).map_err(Into::into)
             },
-            3 => { // Ruido
-                let kernel = p1 as i32;
-                match option {
-                    1 => NoiseReducer::gaussian(img, kernel),
-                    2 => NoiseReducer::median(img, kernel),
-                    _ => Ok(img.clone()),
-                }
+            // Bordes/Canny
+            PipelineStep::Sharpen => EdgeManager::sharpen(img),
+            PipelineStep::Laplacian => EdgeManager::laplacian(img),
+            PipelineStep::Canny { low, high } => EdgeManager::canny(img, *low, *high).map_err(Into::into),
+            // Umbralización (regiones rellenas para los contornos)
+            PipelineStep::Threshold { value, invert } => Thresholder::fixed(img, *value, *invert).map_err(Into::into),
+            PipelineStep::Otsu { invert } => Thresholder::otsu(img, *invert).map(|(mat, _)| mat).map_err(Into::into),
+            PipelineStep::Triangle { invert } => Thresholder::triangle(img, *invert).map(|(mat, _)| mat).map_err(Into::into),
+            PipelineStep::AdaptiveMean { block_size, c, invert } => {
+                Thresholder::adaptive(img, AdaptiveMethod::Mean, *block_size, *c, *invert).map_err(Into::into)
             },
-            4 => match option { // Bordes/Canny
-                1 => EdgeManager::sharpen(img),
-                3 => EdgeManager::canny(img, p1, p2),
-                _ => Ok(img.clone()),
+            PipelineStep::AdaptiveGaussian { block_size, c, invert } => {
+                Thresholder::adaptive(img, AdaptiveMethod::Gaussian, *block_size, *c, *invert).map_err(Into::into)
             },
-            5 => match option { // Afín
-                1 => NixAffine::translate(img, p1 as f32, p2 as f32),
-                2 => {
-                    let engine = NixAffine::new(img, 1.0)?;
-                    engine.rotate(img, p1)
-                },
-                _ => Ok(img.clone()),
+            // Morfología
+            PipelineStep::Morphology { operation, shape, size, iterations } => {
+                Morphology::apply(img, *operation, *shape, *size, *iterations).map_err(Into::into)
+            },
+            // Afín
+            PipelineStep::Translate { x, y } => NixAffine::translate(img, *x, *y),
+            PipelineStep::Rotate { angle } => {
+                let engine = NixAffine::new(img, 1.0)?;
+                engine.rotate(img, *angle)
             },
-            _ => Ok(img.clone()),
+            // Los contornos devuelven métricas, no solo una imagen: los resuelve el pipeline.
+            PipelineStep::Contours { .. } => Err(opencv::Error::new(
+                core::StsBadArg,
+                "El paso 'contours' no es un filtro de imagen; usa DynamicPipeline::run".to_string(),
+            )),
+            // Necesita la calibración de la cámara, que llega en las opciones de ejecución.
+            PipelineStep::Undistort => Err(opencv::Error::new(
+                core::StsBadArg,
+                "El paso 'undistort' requiere la calibración de la cámara; usa DynamicPipeline::run_with".to_string(),
+            )),
         }
     }
-    /// Procesa una imagen aplicando parámetros dinámicos (tipo, opción, param1, param2)
-    /// Esto permite ajustar Canny y Blur en tiempo real para el reporte[cite: 31].
-    pub fn process(img: &Mat, steps: &[(i32, i32, f64, f64)]) -> Result<Mat> {
-        let mut current_mat = img.clone();
-
-        for (step_type, option, p1, p2) in steps {
-            current_mat = match step_type {
-                // 1. CONVERSIÓN DE COLOR (Punto 2 del procedimiento [cite: 19])
-                1 => match option {
-                    1 => ColorConverter::to_grayscale(&current_mat)?,
-                    2 => ColorConverter::to_hsv(&current_mat)?,
-                    _ => current_mat,
-                },
-                // 2. CORRECCIÓN DE ILUMINACIÓN
-                2 => match option {
-                    1 => IlluminationManager::normalize(&current_mat)?,
-                    3 => IlluminationManager::apply_clahe(&current_mat, *p1, core::Size::new(8, 8))?,
-                    _ => current_mat,
-                },
-                // 3. REDUCCIÓN DE RUIDO (Punto 2: Gaussian Blur [cite: 19])
-                3 => {
-                    let kernel_size = *p1 as i32; // Usamos el parámetro ingresado por el usuario
-                    match option {
-                        1 => NoiseReducer::gaussian(&current_mat, kernel_size)?,
-                        2 => NoiseReducer::median(&current_mat, kernel_size)?,
-                        _ => current_mat,
-                    }
-                },
-                // 4. REALCE DE BORDES Y SEGMENTACIÓN (Punto 3: Canny [cite: 20])
-                4 => match option {
-                    1 => EdgeManager::sharpen(&current_mat)?,
-                    3 => EdgeManager::canny(&current_mat, *p1, *p2)?, // p1=low, p2=high threshold
-                    _ => current_mat,
-                },
-                // 5. TRANSFORMACIONES AFINES
-                5 => match option {
-                    1 => NixAffine::translate(&current_mat, *p1 as f32, *p2 as f32)?,
-                    2 => {
-                        let engine = NixAffine::new(&current_mat, 1.0)?;
-                        engine.rotate(&current_mat, *p1 )?
-                    },
-                    _ => current_mat,
-                },
-                // 6. EXTRACCIÓN DE CONTORNOS (Puntos 4-7 del procedimiento [cite: 21, 22])
-                6 => {
-                    let mut display = Mat::default();
-                    
-                    // Verificamos si la imagen actual son Bordes (1 canal)
-                    if current_mat.channels() == 1 {
-                        // Usamos cvt_color_def para evitar el error de los 5 argumentos
-                        // Esto convierte Grises a BGR para poder dibujar en ROJO [cite: 38]
-                        imgproc::cvt_color_def(&current_mat, &mut display, imgproc::COLOR_GRAY2BGR)?;
-                    } else {
-                        display = current_mat.clone();
-                    }
+}
 
-                    // Ejecutamos la medición y detección (Puntos 4-7 del procedimiento [cite: 21, 22])
-                    let metrics = NixContour::find_and_measure(&current_mat, &mut display, *p1)?;
-
-                    if let Some(largest) = metrics.iter().max_by(|a, b| a.area.partial_cmp(&b.area).unwrap()) {
-                        // Resaltamos el objeto más grande (Punto 7 / Extensión [cite: 27]) 
-                        NixContour::draw_highlight(&mut display, largest)?;
-                        
-                        // Imprimimos los datos para tu tabla del Punto 5 [cite: 22, 23, 24, 25]
-                        println!("✅ Objeto Detectado:");
-                        println!("   - Área: {:.2} px", largest.area);
-                        println!("   - Perímetro: {:.2} px", largest.perimeter);
-                        println!("   - Bounding Box: {}x{} px (Ancho x Alto)", largest.width, largest.height);
-                    }
-                    display 
-                },
-                _ => current_mat,
-            };
-        }
-        Ok(current_mat)
+/// Copia en BGR de 8 bits para dibujar en color (los bordes y las máscaras son de 1 canal) [cite: 38].
+fn to_bgr(img: &Mat) -> Result<Mat> {
+    let mut bgr = Mat::default();
+    match img.channels() {
+        1 => imgproc::cvt_color_def(img, &mut bgr, imgproc::COLOR_GRAY2BGR)?,
+        4 => imgproc::cvt_color_def(img, &mut bgr, imgproc::COLOR_BGRA2BGR)?,
+        _ => bgr = img.clone(),
     }
-}
\ No newline at end of file
+    Ok(bgr)
+}
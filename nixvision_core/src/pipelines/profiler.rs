@@ -0,0 +1,90 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+use crate::pipelines::processing::ProcessResult;
+
+/// Estadísticas de tiempo de un paso (o del frame completo), en milisegundos.
+#[derive(Debug, Clone)]
+pub struct TimingStats {
+    pub label: String,
+    pub samples: usize,
+    pub mean_ms: f64,
+    pub p95_ms: f64,
+    pub max_ms: f64,
+}
+
+/// Acumula los tiempos por paso y por frame de varias ejecuciones del pipeline.
+/// Los pasos se agrupan por posición y tipo (`3. canny`), así una búsqueda que
+/// varía parámetros sigue agregando en la misma fila.
+#[derive(Debug, Default)]
+pub struct Profiler {
+    steps: Vec<(String, Vec<Duration>)>,
+    frames: Vec<Duration>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Los pasos servidos desde la caché de prefijos no se cuentan: no se ejecutaron.
+    pub fn record(&mut self, res: &ProcessResult) {
+        for report in res.steps.iter().filter(|r| !r.cached) {
+            let label = format!("{}. {}", report.index + 1, report.step.name());
+            match self.steps.iter_mut().find(|(l, _)| *l == label) {
+                Some((_, samples)) => samples.push(report.elapsed),
+                None => self.steps.push((label, vec![report.elapsed])),
+            }
+        }
+        self.frames.push(res.elapsed);
+    }
+
+    pub fn frames(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Una fila por paso y una última fila `frame` con el total por frame.
+    pub fn summary(&self) -> Vec<TimingStats> {
+        let mut rows: Vec<TimingStats> = self.steps
+            .iter()
+            .map(|(label, samples)| stats(label, samples))
+            .collect();
+
+        if !self.frames.is_empty() {
+            rows.push(stats("frame", &self.frames));
+        }
+        rows
+    }
+
+    /// Exporta el resumen como CSV (`paso,muestras,media_ms,p95_ms,max_ms`).
+    pub fn write_csv(&self, path: &Path) -> io::Result<()> {
+        let mut csv = String::from("step,samples,mean_ms,p95_ms,max_ms\n");
+        for row in self.summary() {
+            csv.push_str(&format!(
+                "{},{},{:.3},{:.3},{:.3}\n",
+                row.label, row.samples, row.mean_ms, row.p95_ms, row.max_ms
+            ));
+        }
+        fs::write(path, csv)
+    }
+}
+
+fn stats(label: &str, samples: &[Duration]) -> TimingStats {
+    let mut ms: Vec<f64> = samples.iter().map(|d| d.as_secs_f64() * 1000.0).collect();
+    ms.sort_by(f64::total_cmp);
+
+    let n = ms.len();
+    let mean_ms = if n > 0 { ms.iter().sum::<f64>() / n as f64 } else { 0.0 };
+    // Percentil 95 por rango más cercano
+    let p95_ms = if n > 0 { ms[((0.95 * n as f64).ceil() as usize).clamp(1, n) - 1] } else { 0.0 };
+    let max_ms = ms.last().copied().unwrap_or(0.0);
+
+    TimingStats {
+        label: label.to_string(),
+        samples: n,
+        mean_ms,
+        p95_ms,
+        max_ms,
+    }
+}
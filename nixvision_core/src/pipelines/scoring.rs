@@ -0,0 +1,179 @@
+use opencv::{core::{Mat, Rect}, prelude::*, Result};
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use nixvision_lib::filters::color::ColorConverter;
+use nixvision_lib::filters::contour::ContourMetrics;
+use nixvision_lib::filters::metrics::QualityMetrics;
+
+use crate::pipelines::sweep::SweepResult;
+
+/// Pesos y parámetros de la puntuación del Auto-Tune (sección `[scoring]` del barrido).
+/// Cada criterio se normaliza a 0..1; un peso 0 lo desactiva.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ScoringConfig {
+    /// Solidez del objeto principal (área / envolvente convexa).
+    pub solidity: f64,
+    /// Cercanía del área detectada a `expected_area`.
+    pub area: f64,
+    /// Fracción de combinaciones vecinas en la rejilla que detectan el mismo objeto.
+    pub stability: f64,
+    /// Nitidez (varianza del Laplaciano) de la imagen original dentro del bounding box.
+    pub sharpness: f64,
+    /// Área esperada del objeto en px²; sin ella el criterio `area` no se usa.
+    pub expected_area: Option<f64>,
+    /// Cuántas combinaciones conservar (imágenes guardadas).
+    pub top_n: usize,
+}
+
+impl Default for ScoringConfig {
+    fn default() -> Self {
+        Self {
+            solidity: 1.0,
+            area: 1.0,
+            stability: 1.0,
+            sharpness: 0.5,
+            expected_area: None,
+            top_n: 5,
+        }
+    }
+}
+
+/// Puntuación de una combinación, con el detalle de cada criterio (0..1).
+#[derive(Debug, Clone, Default)]
+pub struct Score {
+    pub solidity: f64,
+    pub area: f64,
+    pub stability: f64,
+    pub sharpness: f64,
+    /// Media ponderada de los criterios activos.
+    pub total: f64,
+}
+
+/// Combinación puntuada; `result` indexa el vector devuelto por `Sweep::run`.
+#[derive(Debug, Clone)]
+pub struct RankedCandidate {
+    pub result: usize,
+    pub score: Score,
+}
+
+/// Dos detecciones se consideran el mismo objeto si sus bounding boxes se solapan al menos así (IoU).
+const SAME_OBJECT_IOU: f64 = 0.5;
+
+pub struct Scorer;
+
+impl Scorer {
+    /// Puntúa las combinaciones que detectaron algo y las ordena de mejor a peor.
+    /// `img` es la imagen de entrada del barrido (para medir la nitidez del objeto).
+    pub fn rank(img: &Mat, results: &[SweepResult], config: &ScoringConfig) -> Result<Vec<RankedCandidate>> {
+        let gray = if img.channels() == 1 { img.clone() } else { ColorConverter::to_grayscale(img)? };
+
+        // Vecinos en la rejilla: mismas coordenadas salvo un eje desplazado en ±1
+        let by_coords: HashMap<&[usize], &SweepResult> = results
+            .iter()
+            .map(|r| (r.candidate.coords.as_slice(), r))
+            .collect();
+
+        let mut ranked = Vec::new();
+        let mut sharpness = Vec::new();
+
+        for (i, result) in results.iter().enumerate() {
+            let Some(primary) = &result.primary else { continue };
+
+            let area = match config.expected_area {
+                Some(expected) if expected > 0.0 => {
+                    // 1.0 en el área esperada; 0.5 al doble o a la mitad
+                    1.0 / (1.0 + (primary.area / expected).log2().abs())
+                }
+                _ => 0.0,
+            };
+
+            ranked.push(RankedCandidate {
+                result: i,
+                score: Score {
+                    solidity: primary.solidity.clamp(0.0, 1.0),
+                    area,
+                    stability: Self::stability(result, primary, &by_coords),
+                    ..Score::default()
+                },
+            });
+            sharpness.push(if config.sharpness > 0.0 { Self::sharpness(&gray, primary.bbox)? } else { 0.0 });
+        }
+
+        // La nitidez no tiene escala absoluta: se normaliza respecto a la mejor combinación
+        let max_sharpness = sharpness.iter().copied().fold(0.0, f64::max);
+        let area_weight = if config.expected_area.is_some() { config.area } else { 0.0 };
+        let weights = config.solidity + area_weight + config.stability + config.sharpness;
+
+        for (candidate, sharp) in ranked.iter_mut().zip(sharpness) {
+            let score = &mut candidate.score;
+            score.sharpness = if max_sharpness > 0.0 { sharp / max_sharpness } else { 0.0 };
+            score.total = if weights > 0.0 {
+                (config.solidity * score.solidity
+                    + area_weight * score.area
+                    + config.stability * score.stability
+                    + config.sharpness * score.sharpness)
+                    / weights
+            } else {
+                0.0
+            };
+        }
+
+        // Empates: se conserva el orden del barrido
+        ranked.sort_by(|a, b| b.score.total.total_cmp(&a.score.total).then(a.result.cmp(&b.result)));
+        Ok(ranked)
+    }
+
+    fn stability(result: &SweepResult, primary: &ContourMetrics, by_coords: &HashMap<&[usize], &SweepResult>) -> f64 {
+        let coords = &result.candidate.coords;
+        let mut neighbours = 0;
+        let mut agree = 0;
+
+        for axis in 0..coords.len() {
+            for delta in [-1isize, 1] {
+                let Some(value) = coords[axis].checked_add_signed(delta) else { continue };
+                let mut key = coords.clone();
+                key[axis] = value;
+
+                // En búsqueda aleatoria muchos vecinos no se evaluaron: solo cuentan los existentes
+                let Some(neighbour) = by_coords.get(key.as_slice()) else { continue };
+                if neighbour.error.is_some() {
+                    continue;
+                }
+                neighbours += 1;
+                if let Some(other) = &neighbour.primary
+                    && iou(primary.bbox, other.bbox) >= SAME_OBJECT_IOU
+                {
+                    agree += 1;
+                }
+            }
+        }
+
+        if neighbours > 0 { agree as f64 / neighbours as f64 } else { 0.0 }
+    }
+
+    fn sharpness(gray: &Mat, bbox: Rect) -> Result<f64> {
+        let size = gray.size()?;
+        let x = bbox.x.clamp(0, size.width);
+        let y = bbox.y.clamp(0, size.height);
+        let roi = Rect::new(x, y, (bbox.x + bbox.width).min(size.width) - x, (bbox.y + bbox.height).min(size.height) - y);
+        // Las transformaciones afines pueden dejar el objeto fuera de la imagen original
+        if roi.width <= 0 || roi.height <= 0 {
+            return Ok(0.0);
+        }
+
+        let crop = Mat::roi(gray, roi)?.try_clone()?;
+        let (_, sharpness, _) = QualityMetrics::calculate(&crop)?;
+        Ok(sharpness)
+    }
+}
+
+fn iou(a: Rect, b: Rect) -> f64 {
+    let w = (a.x + a.width).min(b.x + b.width) - a.x.max(b.x);
+    let h = (a.y + a.height).min(b.y + b.height) - a.y.max(b.y);
+    if w <= 0 || h <= 0 {
+        return 0.0;
+    }
+    let inter = (w * h) as f64;
+    inter / ((a.width * a.height + b.width * b.height) as f64 - inter)
+}
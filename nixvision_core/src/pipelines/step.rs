@@ -0,0 +1,333 @@
+use serde::{Serialize, Deserialize};
+use std::fmt;
+use nixvision_lib::error::NixResult;
+use nixvision_lib::filters::{
+    contour::{ContourFilter, ContourMode, Selection}, edges::EdgeManager, illumination::IlluminationManager, morphology::{KernelShape, MorphOp, Morphology},
+    noise::NoiseReducer, threshold::Thresholder,
+};
+use opencv::core;
+
+/// Paso tipado del pipeline.
+/// Sustituye a las tuplas `(tipo, opción, p1, p2)`: cada variante lleva sus propios
+/// parámetros con nombre, así una receta serializada se explica sola y un paso
+/// desconocido es un error de parseo en lugar de un `img.clone()` silencioso.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum PipelineStep {
+    /// BGR -> Escala de grises.
+    Grayscale,
+    /// BGR -> HSV.
+    Hsv,
+    /// Canal V (brillo) de una imagen HSV.
+    ValueChannel,
+    /// Normalización Min-Max al rango [0, 255].
+    Normalize,
+    /// Corrección de fondo (flat-field aproximado con blur grande).
+    BackgroundCorrection,
+    /// CLAHE con límite de recorte y rejilla de `grid` x `grid` celdas.
+    Clahe {
+        clip_limit: f64,
+        #[serde(default = "default_clahe_grid")]
+        grid: i32,
+    },
+    /// Desenfoque Gaussiano con kernel cuadrado.
+    GaussianBlur { kernel: i32 },
+    /// Filtro de mediana (sal y pimienta).
+    MedianBlur { kernel: i32 },
+    /// Filtro bilateral: reduce ruido preservando bordes.
+    Bilateral { diameter: i32, sigma_color: f64, sigma_space: f64 },
+    /// Afilado con máscara de convolución 3x3.
+    Sharpen,
+    /// Laplaciano 3x3 en valor absoluto (8 bits).
+    Laplacian,
+    /// Detector de bordes Canny con umbrales bajo/alto.
+    Canny { low: f64, high: f64 },
+    /// Umbral global fijo (0-255); `invert` deja el objeto oscuro en blanco.
+    Threshold {
+        value: f64,
+        #[serde(default)]
+        invert: bool,
+    },
+    /// Umbral global automático de Otsu (histograma bimodal).
+    Otsu {
+        #[serde(default)]
+        invert: bool,
+    },
+    /// Umbral global automático del triángulo (un pico dominante).
+    Triangle {
+        #[serde(default)]
+        invert: bool,
+    },
+    /// Umbral local con la media de una vecindad `block_size` x `block_size` menos `c`.
+    AdaptiveMean {
+        block_size: i32,
+        c: f64,
+        #[serde(default)]
+        invert: bool,
+    },
+    /// Umbral local con media gaussiana de la vecindad menos `c`.
+    AdaptiveGaussian {
+        block_size: i32,
+        c: f64,
+        #[serde(default)]
+        invert: bool,
+    },
+    /// Operación morfológica (`operation`: erode, dilate, open, close, gradient, top_hat)
+    /// con un elemento `shape` de `size` x `size` píxeles, repetida `iterations` veces.
+    Morphology {
+        operation: MorphOp,
+        #[serde(default)]
+        shape: KernelShape,
+        size: i32,
+        #[serde(default = "default_iterations")]
+        iterations: i32,
+    },
+    /// Corrección de la distorsión de lente con la calibración de la cámara (va primero).
+    Undistort,
+    /// Traslación en píxeles.
+    Translate { x: f32, y: f32 },
+    /// Rotación en grados alrededor del centro de la imagen.
+    Rotate { angle: f64 },
+    /// Extracción de contornos y métricas. Los criterios del filtro (`min_area`, `max_area`,
+    /// `min_circularity`, `exclude_border`...) van en el mismo paso, y `select` decide qué
+    /// objetos se conservan (`largest`, `{ k_largest = 3 }`, `all`, `closest_to_center`).
+    /// `mode = "tree"` recupera también los agujeros de cada objeto; `level` limita
+    /// la medición a una profundidad del árbol (0 = exteriores, 1 = agujeros...).
+    Contours {
+        #[serde(flatten)]
+        filter: ContourFilter,
+        #[serde(default)]
+        mode: ContourMode,
+        #[serde(default)]
+        level: Option<i32>,
+        #[serde(default)]
+        select: Selection,
+    },
+}
+
+fn default_clahe_grid() -> i32 {
+    8
+}
+
+fn default_iterations() -> i32 {
+    1
+}
+
+impl PipelineStep {
+    /// Nombre corto del paso (coincide con la etiqueta `op` serializada).
+    pub fn name(&self) -> &'static str {
+        match self {
+            PipelineStep::Grayscale => "grayscale",
+            PipelineStep::Hsv => "hsv",
+            PipelineStep::ValueChannel => "value_channel",
+            PipelineStep::Normalize => "normalize",
+            PipelineStep::BackgroundCorrection => "background_correction",
+            PipelineStep::Clahe { .. } => "clahe",
+            PipelineStep::GaussianBlur { .. } => "gaussian_blur",
+            PipelineStep::MedianBlur { .. } => "median_blur",
+            PipelineStep::Bilateral { .. } => "bilateral",
+            PipelineStep::Sharpen => "sharpen",
+            PipelineStep::Laplacian => "laplacian",
+            PipelineStep::Canny { .. } => "canny",
+            PipelineStep::Threshold { .. } => "threshold",
+            PipelineStep::Otsu { .. } => "otsu",
+            PipelineStep::Triangle { .. } => "triangle",
+            PipelineStep::AdaptiveMean { .. } => "adaptive_mean",
+            PipelineStep::AdaptiveGaussian { .. } => "adaptive_gaussian",
+            PipelineStep::Morphology { .. } => "morphology",
+            PipelineStep::Undistort => "undistort",
+            PipelineStep::Translate { .. } => "translate",
+            PipelineStep::Rotate { .. } => "rotate",
+            PipelineStep::Contours { .. } => "contours",
+        }
+    }
+
+    /// Transformación afín: mueve los píxeles respecto al frame original.
+    pub fn is_affine(&self) -> bool {
+        matches!(self, PipelineStep::Translate { .. } | PipelineStep::Rotate { .. })
+    }
+
+    /// Comprueba los parámetros del paso con las mismas reglas que aplica cada filtro.
+    pub fn check_params(&self) -> NixResult<()> {
+        match self {
+            PipelineStep::Clahe { clip_limit, grid } => {
+                IlluminationManager::check_clahe(*clip_limit, core::Size::new(*grid, *grid))
+            }
+            PipelineStep::GaussianBlur { kernel } => NoiseReducer::check_gaussian(*kernel),
+            PipelineStep::MedianBlur { kernel } => NoiseReducer::check_median(*kernel),
+            PipelineStep::Bilateral { diameter, sigma_color, sigma_space } => {
+                NoiseReducer::check_bilateral(*diameter, *sigma_color, *sigma_space)
+            }
+            PipelineStep::Canny { low, high } => EdgeManager::check_canny(*low, *high),
+            PipelineStep::Threshold { value, .. } => Thresholder::check_fixed(*value),
+            PipelineStep::AdaptiveMean { block_size, .. } | PipelineStep::AdaptiveGaussian { block_size, .. } => {
+                Thresholder::check_adaptive(*block_size)
+            }
+            PipelineStep::Morphology { size, iterations, .. } => Morphology::check_morphology(*size, *iterations),
+            PipelineStep::Contours { filter, select, .. } => {
+                filter.check()?;
+                select.check()
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Parámetros numéricos del paso como `(nombre, valor)`; son los que puede variar un barrido.
+    pub fn params(&self) -> Vec<(&'static str, f64)> {
+        match self {
+            PipelineStep::Clahe { clip_limit, grid } => vec![("clip_limit", *clip_limit), ("grid", *grid as f64)],
+            PipelineStep::GaussianBlur { kernel } | PipelineStep::MedianBlur { kernel } => vec![("kernel", *kernel as f64)],
+            PipelineStep::Bilateral { diameter, sigma_color, sigma_space } => vec![
+                ("diameter", *diameter as f64),
+                ("sigma_color", *sigma_color),
+                ("sigma_space", *sigma_space),
+            ],
+            PipelineStep::Canny { low, high } => vec![("low", *low), ("high", *high)],
+            PipelineStep::Threshold { value, .. } => vec![("value", *value)],
+            PipelineStep::AdaptiveMean { block_size, c, .. } | PipelineStep::AdaptiveGaussian { block_size, c, .. } => {
+                vec![("block_size", *block_size as f64), ("c", *c)]
+            }
+            PipelineStep::Morphology { size, iterations, .. } => vec![("size", *size as f64), ("iterations", *iterations as f64)],
+            PipelineStep::Translate { x, y } => vec![("x", *x as f64), ("y", *y as f64)],
+            PipelineStep::Rotate { angle } => vec![("angle", *angle)],
+            PipelineStep::Contours { filter, .. } => {
+                // Los límites opcionales solo se listan si están activos
+                let optional = [
+                    ("max_area", filter.max_area),
+                    ("min_perimeter", filter.min_perimeter),
+                    ("max_perimeter", filter.max_perimeter),
+                    ("min_circularity", filter.min_circularity),
+                    ("max_circularity", filter.max_circularity),
+                    ("min_aspect_ratio", filter.min_aspect_ratio),
+                    ("max_aspect_ratio", filter.max_aspect_ratio),
+                    ("min_solidity", filter.min_solidity),
+                ];
+                std::iter::once(("min_area", filter.min_area))
+                    .chain(optional.into_iter().filter_map(|(name, value)| value.map(|v| (name, v))))
+                    .collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Cambia un parámetro por nombre (los enteros se redondean).
+    pub fn set_param(&mut self, name: &str, value: f64) -> Result<(), String> {
+        let int = value.round() as i32;
+        match (self, name) {
+            (PipelineStep::Clahe { clip_limit, .. }, "clip_limit") => *clip_limit = value,
+            (PipelineStep::Clahe { grid, .. }, "grid") => *grid = int,
+            (PipelineStep::GaussianBlur { kernel } | PipelineStep::MedianBlur { kernel }, "kernel") => *kernel = int,
+            (PipelineStep::Bilateral { diameter, .. }, "diameter") => *diameter = int,
+            (PipelineStep::Bilateral { sigma_color, .. }, "sigma_color") => *sigma_color = value,
+            (PipelineStep::Bilateral { sigma_space, .. }, "sigma_space") => *sigma_space = value,
+            (PipelineStep::Canny { low, .. }, "low") => *low = value,
+            (PipelineStep::Canny { high, .. }, "high") => *high = value,
+            (PipelineStep::Threshold { value: v, .. }, "value") => *v = value,
+            (
+                PipelineStep::AdaptiveMean { block_size, .. } | PipelineStep::AdaptiveGaussian { block_size, .. },
+                "block_size",
+            ) => *block_size = int,
+            (PipelineStep::AdaptiveMean { c, .. } | PipelineStep::AdaptiveGaussian { c, .. }, "c") => *c = value,
+            (PipelineStep::Morphology { size, .. }, "size") => *size = int,
+            (PipelineStep::Morphology { iterations, .. }, "iterations") => *iterations = int,
+            (PipelineStep::Translate { x, .. }, "x") => *x = value as f32,
+            (PipelineStep::Translate { y, .. }, "y") => *y = value as f32,
+            (PipelineStep::Rotate { angle }, "angle") => *angle = value,
+            (PipelineStep::Contours { filter, .. }, "min_area") => filter.min_area = value,
+            (PipelineStep::Contours { filter, .. }, "max_area") => filter.max_area = Some(value),
+            (PipelineStep::Contours { filter, .. }, "min_perimeter") => filter.min_perimeter = Some(value),
+            (PipelineStep::Contours { filter, .. }, "max_perimeter") => filter.max_perimeter = Some(value),
+            (PipelineStep::Contours { filter, .. }, "min_circularity") => filter.min_circularity = Some(value),
+            (PipelineStep::Contours { filter, .. }, "max_circularity") => filter.max_circularity = Some(value),
+            (PipelineStep::Contours { filter, .. }, "min_aspect_ratio") => filter.min_aspect_ratio = Some(value),
+            (PipelineStep::Contours { filter, .. }, "max_aspect_ratio") => filter.max_aspect_ratio = Some(value),
+            (PipelineStep::Contours { filter, .. }, "min_solidity") => filter.min_solidity = Some(value),
+            (step, _) => {
+                let available: Vec<&str> = step.params().iter().map(|(n, _)| *n).collect();
+                return Err(format!(
+                    "el paso '{}' no tiene el parámetro '{}' (disponibles: {})",
+                    step.name(),
+                    name,
+                    if available.is_empty() { "ninguno".to_string() } else { available.join(", ") }
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Versión del paso apta para nombres de archivo: `canny(low=50, high=150)` -> `canny_low50_high150`.
+    pub fn slug(&self) -> String {
+        self.to_string()
+            .chars()
+            .filter_map(|c| match c {
+                '(' | ',' => Some('_'),
+                ' ' | '=' | ')' => None,
+                other => Some(other),
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for PipelineStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PipelineStep::Clahe { clip_limit, grid } => write!(f, "clahe(clip={}, grid={})", clip_limit, grid),
+            PipelineStep::GaussianBlur { kernel } => write!(f, "gaussian_blur(k={})", kernel),
+            PipelineStep::MedianBlur { kernel } => write!(f, "median_blur(k={})", kernel),
+            PipelineStep::Bilateral { diameter, sigma_color, sigma_space } => {
+                write!(f, "bilateral(d={}, sigma_color={}, sigma_space={})", diameter, sigma_color, sigma_space)
+            }
+            PipelineStep::Canny { low, high } => write!(f, "canny(low={}, high={})", low, high),
+            PipelineStep::Threshold { value, invert } => write!(f, "threshold(value={}{})", value, inv(*invert)),
+            PipelineStep::Otsu { invert: true } | PipelineStep::Triangle { invert: true } => write!(f, "{}(inv)", self.name()),
+            PipelineStep::AdaptiveMean { block_size, c, invert } | PipelineStep::AdaptiveGaussian { block_size, c, invert } => {
+                write!(f, "{}(block={}, c={}{})", self.name(), block_size, c, inv(*invert))
+            }
+            PipelineStep::Morphology { operation, shape, size, iterations } => {
+                write!(f, "{}({}, size={}, it={})", operation.name(), shape.name(), size, iterations)
+            }
+            PipelineStep::Translate { x, y } => write!(f, "translate(x={}, y={})", x, y),
+            PipelineStep::Rotate { angle } => write!(f, "rotate(angle={})", angle),
+            PipelineStep::Contours { filter, mode, level, select } => {
+                write!(f, "contours(min_area={}", filter.min_area)?;
+                let limits = [
+                    ("max_area", filter.max_area),
+                    ("min_perim", filter.min_perimeter),
+                    ("max_perim", filter.max_perimeter),
+                    ("min_circ", filter.min_circularity),
+                    ("max_circ", filter.max_circularity),
+                    ("min_ar", filter.min_aspect_ratio),
+                    ("max_ar", filter.max_aspect_ratio),
+                    ("min_solidity", filter.min_solidity),
+                ];
+                for (name, value) in limits {
+                    if let Some(value) = value {
+                        write!(f, ", {}={}", name, value)?;
+                    }
+                }
+                if filter.exclude_border {
+                    write!(f, ", no_border")?;
+                }
+                if *mode == ContourMode::Tree {
+                    write!(f, ", tree")?;
+                }
+                if let Some(level) = level {
+                    write!(f, ", level={}", level)?;
+                }
+                match select {
+                    Selection::Largest => {}
+                    Selection::KLargest(k) => write!(f, ", k_largest={}", k)?,
+                    Selection::All => write!(f, ", all")?,
+                    Selection::ClosestToCenter => write!(f, ", center")?,
+                }
+                write!(f, ")")
+            }
+            _ => write!(f, "{}", self.name()),
+        }
+    }
+}
+
+/// Sufijo de los umbrales invertidos en `Display`.
+fn inv(invert: bool) -> &'static str {
+    if invert { ", inv" } else { "" }
+}
@@ -0,0 +1,528 @@
+use opencv::{core::Mat, prelude::*, Result};
+use rayon::prelude::*;
+use serde::{Serialize, Deserialize};
+use std::collections::HashSet;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+use nixvision_lib::filters::contour::{ContourFilter, ContourMetrics, ContourMode, Selection};
+
+use crate::config::format::{load_document, save_document};
+use crate::pipelines::scoring::ScoringConfig;
+use crate::pipelines::cache::PrefixCache;
+use crate::pipelines::parallel::worker_pool;
+use crate::pipelines::processing::{DynamicPipeline, PipelineOptions, ProcessResult};
+use crate::pipelines::step::PipelineStep;
+use crate::pipelines::validation::{ImageFormat, RecipeValidator, Validation};
+
+/// Memoria máxima para imágenes intermedias durante un barrido (512 MiB).
+const MAX_CACHE_BYTES: usize = 512 * 1024 * 1024;
+
+/// Valores que toma un parámetro durante el barrido.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum ValueSpec {
+    /// Lista explícita: `{ values = [3, 5, 7] }`.
+    Values(Vec<f64>),
+    /// `num` valores equiespaciados entre `start` y `stop` (ambos incluidos).
+    Linspace { start: f64, stop: f64, num: usize },
+    /// `num` valores entre `10^start` y `10^stop`, equiespaciados en escala logarítmica.
+    Logspace { start: f64, stop: f64, num: usize },
+}
+
+impl ValueSpec {
+    pub fn values(&self) -> Vec<f64> {
+        match self {
+            ValueSpec::Values(values) => values.clone(),
+            ValueSpec::Linspace { start, stop, num } => linspace(*start, *stop, *num),
+            ValueSpec::Logspace { start, stop, num } => {
+                linspace(*start, *stop, *num).into_iter().map(|e| 10f64.powf(e)).collect()
+            }
+        }
+    }
+}
+
+fn linspace(start: f64, stop: f64, num: usize) -> Vec<f64> {
+    match num {
+        0 => Vec::new(),
+        1 => vec![start],
+        _ => (0..num)
+            .map(|i| start + (stop - start) * i as f64 / (num - 1) as f64)
+            .collect(),
+    }
+}
+
+/// Un eje del barrido: un parámetro de un paso de la receta.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SweepAxis {
+    /// Posición del paso en la receta, empezando en 1 (como se muestra en el editor).
+    pub step: usize,
+    pub param: String,
+    pub range: ValueSpec,
+    /// Los ejes con el mismo `link` avanzan juntos (por ejemplo `low`/`high` de Canny)
+    /// en lugar de combinarse entre sí; deben tener el mismo número de valores.
+    #[serde(default)]
+    pub link: Option<String>,
+}
+
+/// Estrategia de búsqueda.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum SearchMode {
+    /// Todas las combinaciones.
+    #[default]
+    Grid,
+    /// `samples` combinaciones distintas elegidas al azar (reproducibles con `seed`).
+    Random {
+        samples: usize,
+        #[serde(default)]
+        seed: u64,
+    },
+}
+
+/// Definición declarativa de un barrido de parámetros, cargable desde TOML/JSON.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SweepSpec {
+    #[serde(default)]
+    pub name: String,
+    pub recipe: Vec<PipelineStep>,
+    pub axes: Vec<SweepAxis>,
+    #[serde(default)]
+    pub search: SearchMode,
+    /// Cómo se puntúan y seleccionan las mejores combinaciones.
+    #[serde(default)]
+    pub scoring: ScoringConfig,
+}
+
+impl SweepSpec {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        load_document(path)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        save_document(self, path)
+    }
+
+    /// Búsqueda clásica del Auto-Tune: 6 blurs x 9 rangos de Canny x 6 áreas mínimas.
+    pub fn autotune_default() -> Self {
+        let values = |v: &[f64]| ValueSpec::Values(v.to_vec());
+        let canny = Some("canny".to_string());
+
+        Self {
+            name: "autotune".to_string(),
+            recipe: vec![
+                PipelineStep::Grayscale,
+                PipelineStep::GaussianBlur { kernel: 5 },
+                PipelineStep::Canny { low: 50.0, high: 100.0 },
+                PipelineStep::Contours {
+                    filter: ContourFilter::min_area(500.0),
+                    mode: ContourMode::External,
+                    level: None,
+                    select: Selection::Largest,
+                },
+            ],
+            axes: vec![
+                SweepAxis { step: 2, param: "kernel".to_string(), range: values(&[3.0, 5.0, 7.0, 9.0, 11.0, 13.0]), link: None },
+                SweepAxis {
+                    step: 3,
+                    param: "low".to_string(),
+                    range: values(&[10.0, 20.0, 30.0, 50.0, 70.0, 80.0, 40.0, 60.0, 90.0]),
+                    link: canny.clone(),
+                },
+                SweepAxis {
+                    step: 3,
+                    param: "high".to_string(),
+                    range: values(&[40.0, 60.0, 80.0, 100.0, 150.0, 200.0, 50.0, 70.0, 100.0]),
+                    link: canny,
+                },
+                SweepAxis {
+                    step: 4,
+                    param: "min_area".to_string(),
+                    range: values(&[100.0, 300.0, 500.0, 1000.0, 2000.0, 4000.0]),
+                    link: None,
+                },
+            ],
+            search: SearchMode::Grid,
+            scoring: ScoringConfig::default(),
+        }
+    }
+}
+
+/// Valor asignado a un parámetro en una combinación concreta.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParamValue {
+    pub step: usize,
+    pub param: String,
+    pub value: f64,
+}
+
+/// Una combinación del barrido, lista para ejecutarse.
+#[derive(Debug, Clone)]
+pub struct SweepCandidate {
+    /// Posición dentro del barrido (orden de la rejilla).
+    pub index: usize,
+    /// Índice elegido en cada grupo de ejes; sirve para encontrar vecinos en la rejilla.
+    pub coords: Vec<usize>,
+    pub assignments: Vec<ParamValue>,
+    pub recipe: Vec<PipelineStep>,
+    /// Si los parámetros no son válidos (ej. Canny low >= high) la combinación no se ejecuta.
+    pub invalid: Option<String>,
+}
+
+impl SweepCandidate {
+    /// Etiqueta apta para nombres de archivo, p. ej. `kernel5_low50_high100_min_area500`.
+    /// Si dos ejes comparten nombre de parámetro se antepone el paso (`s2kernel5`).
+    pub fn label(&self) -> String {
+        self.assignments
+            .iter()
+            .map(|a| {
+                let ambiguous = self.assignments.iter().filter(|b| b.param == a.param).count() > 1;
+                if ambiguous {
+                    format!("s{}{}{}", a.step, a.param, a.value)
+                } else {
+                    format!("{}{}", a.param, a.value)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("_")
+    }
+}
+
+/// Resultado resumido de una combinación (sin imagen, para no retener cientos de Mats).
+#[derive(Debug, Clone)]
+pub struct SweepResult {
+    pub candidate: SweepCandidate,
+    /// Objeto principal (según la selección del paso de contornos), si hubo detección.
+    pub primary: Option<ContourMetrics>,
+    pub objects: usize,
+    /// Tiempo de los pasos ejecutados (sin los `cached_steps` reutilizados de la caché).
+    pub elapsed: Duration,
+    pub cached_steps: usize,
+    pub error: Option<String>,
+}
+
+impl SweepResult {
+    pub fn detected(&self) -> bool {
+        self.primary.is_some()
+    }
+}
+
+/// Motor de barridos: expande un `SweepSpec` en combinaciones y las ejecuta con `DynamicPipeline`.
+pub struct Sweep {
+    pub spec: SweepSpec,
+    /// Tamaño de cada grupo de ejes (la rejilla completa es su producto).
+    pub shape: Vec<usize>,
+    candidates: Vec<SweepCandidate>,
+}
+
+impl Sweep {
+    /// Valida los ejes y genera las combinaciones según la estrategia de búsqueda.
+    pub fn new(spec: SweepSpec) -> std::result::Result<Self, String> {
+        // 1. Validación de ejes
+        for axis in &spec.axes {
+            let Some(step) = axis.step.checked_sub(1).and_then(|i| spec.recipe.get(i)) else {
+                return Err(format!("el eje '{}' apunta al paso {}, pero la receta tiene {} pasos", axis.param, axis.step, spec.recipe.len()));
+            };
+            step.clone().set_param(&axis.param, 0.0).map_err(|e| format!("paso {}: {}", axis.step, e))?;
+            if axis.range.values().is_empty() {
+                return Err(format!("el eje '{}' del paso {} no tiene valores", axis.param, axis.step));
+            }
+        }
+
+        // 2. Agrupamos los ejes enlazados (mismo `link`) para que avancen juntos
+        let mut groups: Vec<(Option<String>, Vec<&SweepAxis>)> = Vec::new();
+        for axis in &spec.axes {
+            match groups.iter_mut().find(|(link, _)| axis.link.is_some() && *link == axis.link) {
+                Some((_, members)) => members.push(axis),
+                None => groups.push((axis.link.clone(), vec![axis])),
+            }
+        }
+
+        let mut shape = Vec::with_capacity(groups.len());
+        let mut group_values = Vec::with_capacity(groups.len());
+        for (link, members) in &groups {
+            let values: Vec<Vec<f64>> = members.iter().map(|a| a.range.values()).collect();
+            let len = values[0].len();
+            if values.iter().any(|v| v.len() != len) {
+                return Err(format!("los ejes enlazados '{}' deben tener el mismo número de valores", link.as_deref().unwrap_or("")));
+            }
+            shape.push(len);
+            group_values.push(values);
+        }
+
+        // 3. Selección de combinaciones (índices planos de la rejilla)
+        let total: usize = shape.iter().product();
+        let flat: Vec<usize> = match spec.search {
+            SearchMode::Grid => (0..total).collect(),
+            SearchMode::Random { samples, .. } if samples >= total => (0..total).collect(),
+            SearchMode::Random { samples, seed } => {
+                let mut rng = SplitMix64(seed);
+                let mut chosen = HashSet::with_capacity(samples);
+                while chosen.len() < samples {
+                    chosen.insert(rng.below(total));
+                }
+                // Orden de rejilla: resultados estables y pasos comunes contiguos
+                let mut flat: Vec<usize> = chosen.into_iter().collect();
+                flat.sort_unstable();
+                flat
+            }
+        };
+
+        // 4. Construcción de cada receta
+        let candidates = flat
+            .into_iter()
+            .enumerate()
+            .map(|(index, flat_index)| {
+                let coords = unravel(flat_index, &shape);
+                let mut recipe = spec.recipe.clone();
+                let mut assignments = Vec::new();
+
+                for (g, (_, members)) in groups.iter().enumerate() {
+                    for (m, axis) in members.iter().enumerate() {
+                        let value = group_values[g][m][coords[g]];
+                        // Ya validado arriba: el parámetro existe en el paso
+                        let _ = recipe[axis.step - 1].set_param(&axis.param, value);
+                        assignments.push(ParamValue { step: axis.step, param: axis.param.clone(), value });
+                    }
+                }
+
+                let invalid = recipe
+                    .iter()
+                    .find_map(|step| step.check_params().err())
+                    .map(|e| e.to_string());
+
+                SweepCandidate { index, coords, assignments, recipe, invalid }
+            })
+            .collect();
+
+        Ok(Self { spec, shape, candidates })
+    }
+
+    pub fn candidates(&self) -> &[SweepCandidate] {
+        &self.candidates
+    }
+
+    pub fn total(&self) -> usize {
+        self.candidates.len()
+    }
+
+    /// Comprueba la receta del barrido contra el formato de la imagen de entrada, sin
+    /// correcciones automáticas (los ejes apuntan a posiciones fijas de la receta).
+    /// Los ejes solo cambian valores numéricos, así que basta con la primera combinación válida.
+    pub fn validate(&self, input: ImageFormat) -> Validation {
+        let recipe = self
+            .candidates
+            .iter()
+            .find(|c| c.invalid.is_none())
+            .map_or(&self.spec.recipe, |c| &c.recipe);
+        RecipeValidator::check(recipe, input, false)
+    }
+
+    /// Ejecuta todas las combinaciones sobre `img` repartidas en `threads` hilos (0 = todos
+    /// los núcleos). Una combinación que falla en OpenCV queda registrada en su `error` sin
+    /// detener el resto; conviene validar antes la receta con `validate`.
+    /// `on_result` recibe cada combinación ejecutada desde el hilo que la terminó, con su
+    /// resultado completo (con imagen) o `None` si falló, para guardar, perfilar o mostrar
+    /// progreso; debe ser seguro entre hilos. Las combinaciones inválidas no se ejecutan ni
+    /// lo llaman (ver `runnable`). Los resultados se devuelven siempre en el orden del barrido.
+    /// `options` se aplica a todas las combinaciones (anotación, escala, calibración de lente);
+    /// con `options.debug_dir`, cada una vuelca sus etapas en `debug_dir/{etiqueta}`.
+    /// Las etapas comunes (p. ej. grises + blur) se calculan una vez gracias a `cache`,
+    /// que debe ser nueva para cada imagen de entrada.
+    pub fn run<F>(&self, img: &Mat, options: &PipelineOptions, threads: usize, cache: &PrefixCache, on_result: F) -> Result<Vec<SweepResult>>
+    where
+        F: Fn(&SweepCandidate, Option<&ProcessResult>) -> Result<()> + Sync,
+    {
+        worker_pool(threads)?.install(|| {
+            self.candidates
+                .par_iter()
+                .map(|candidate| self.run_candidate(candidate, img, options, cache, &on_result))
+                .collect()
+        })
+    }
+
+    /// Presupuesto en bytes para la caché de un barrido sobre `img`: un prefijo por paso
+    /// de cada combinación como máximo, sin pasar de `MAX_CACHE_BYTES`.
+    pub fn cache_capacity(&self, img: &Mat) -> usize {
+        let frame = img.total() * img.elem_size().unwrap_or(0);
+        frame.saturating_mul(self.candidates.len() * self.spec.recipe.len()).min(MAX_CACHE_BYTES)
+    }
+
+    /// Número de combinaciones que se ejecutarán (las inválidas se omiten).
+    pub fn runnable(&self) -> usize {
+        self.candidates.iter().filter(|c| c.invalid.is_none()).count()
+    }
+
+    fn run_candidate<F>(
+        &self,
+        candidate: &SweepCandidate,
+        img: &Mat,
+        options: &PipelineOptions,
+        cache: &PrefixCache,
+        on_result: &F,
+    ) -> Result<SweepResult>
+    where
+        F: Fn(&SweepCandidate, Option<&ProcessResult>) -> Result<()>,
+    {
+        if let Some(reason) = &candidate.invalid {
+            return Ok(SweepResult {
+                candidate: candidate.clone(),
+                primary: None,
+                objects: 0,
+                elapsed: Duration::ZERO,
+                cached_steps: 0,
+                error: Some(reason.clone()),
+            });
+        }
+
+        let options = PipelineOptions {
+            debug_dir: options.debug_dir.as_ref().map(|d| d.join(candidate.label())),
+            montage: options.montage,
+            ..options.clone()
+        };
+        let res = match DynamicPipeline::run_cached(img, &candidate.recipe, &options, cache) {
+            Ok(res) => res,
+            Err(e) => {
+                on_result(candidate, None)?;
+                return Ok(SweepResult {
+                    candidate: candidate.clone(),
+                    primary: None,
+                    objects: 0,
+                    elapsed: Duration::ZERO,
+                    cached_steps: 0,
+                    error: Some(e.message),
+                });
+            }
+        };
+        on_result(candidate, Some(&res))?;
+
+        Ok(SweepResult {
+            candidate: candidate.clone(),
+            primary: res.primary().cloned(),
+            objects: res.objects.len(),
+            elapsed: res.elapsed,
+            cached_steps: res.steps.iter().filter(|s| s.cached).count(),
+            error: None,
+        })
+    }
+}
+
+/// Índice plano -> coordenadas (el primer grupo varía más lento, como bucles anidados).
+fn unravel(mut flat: usize, shape: &[usize]) -> Vec<usize> {
+    let mut coords = vec![0; shape.len()];
+    for (i, size) in shape.iter().enumerate().rev() {
+        coords[i] = flat % size;
+        flat /= size;
+    }
+    coords
+}
+
+/// Generador pseudoaleatorio mínimo (SplitMix64), suficiente para muestrear combinaciones.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn axis(step: usize, param: &str, values: &[f64], link: Option<&str>) -> SweepAxis {
+        SweepAxis {
+            step,
+            param: param.to_string(),
+            range: ValueSpec::Values(values.to_vec()),
+            link: link.map(str::to_string),
+        }
+    }
+
+    fn spec(axes: Vec<SweepAxis>, search: SearchMode) -> SweepSpec {
+        SweepSpec { axes, search, ..SweepSpec::autotune_default() }
+    }
+
+    #[test]
+    fn linspace_includes_both_ends() {
+        assert_eq!(linspace(0.0, 10.0, 5), vec![0.0, 2.5, 5.0, 7.5, 10.0]);
+        assert_eq!(linspace(3.0, 9.0, 1), vec![3.0]);
+        assert!(linspace(0.0, 1.0, 0).is_empty());
+    }
+
+    #[test]
+    fn logspace_uses_powers_of_ten() {
+        let values = ValueSpec::Logspace { start: 0.0, stop: 2.0, num: 3 }.values();
+        let expected = [1.0, 10.0, 100.0];
+        assert_eq!(values.len(), expected.len());
+        for (value, expected) in values.iter().zip(expected) {
+            assert!((value - expected).abs() < 1e-9, "{} != {}", value, expected);
+        }
+    }
+
+    #[test]
+    fn unravel_varies_last_group_fastest() {
+        let shape = [2, 3];
+        let coords: Vec<Vec<usize>> = (0..6).map(|i| unravel(i, &shape)).collect();
+        assert_eq!(coords, vec![vec![0, 0], vec![0, 1], vec![0, 2], vec![1, 0], vec![1, 1], vec![1, 2]]);
+    }
+
+    #[test]
+    fn linked_axes_advance_together() {
+        let sweep = Sweep::new(spec(
+            vec![
+                axis(2, "kernel", &[3.0, 5.0], None),
+                axis(3, "low", &[10.0, 20.0, 30.0], Some("canny")),
+                axis(3, "high", &[40.0, 50.0, 60.0], Some("canny")),
+            ],
+            SearchMode::Grid,
+        ))
+        .unwrap();
+
+        assert_eq!(sweep.shape, vec![2, 3]);
+        assert_eq!(sweep.total(), 6);
+        for candidate in sweep.candidates() {
+            let PipelineStep::Canny { low, high } = candidate.recipe[2] else { panic!("el paso 3 debe ser Canny") };
+            assert_eq!(high - low, 30.0, "low/high desacoplados en {}", candidate.label());
+        }
+    }
+
+    #[test]
+    fn linked_axes_need_the_same_length() {
+        let result = Sweep::new(spec(
+            vec![axis(3, "low", &[10.0, 20.0], Some("canny")), axis(3, "high", &[40.0], Some("canny"))],
+            SearchMode::Grid,
+        ));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn random_search_is_reproducible_and_distinct() {
+        let axes = || vec![axis(2, "kernel", &[3.0, 5.0, 7.0, 9.0], None), axis(4, "min_area", &[100.0, 200.0, 300.0, 400.0, 500.0], None)];
+        let a = Sweep::new(spec(axes(), SearchMode::Random { samples: 7, seed: 42 })).unwrap();
+        let b = Sweep::new(spec(axes(), SearchMode::Random { samples: 7, seed: 42 })).unwrap();
+
+        let coords = |s: &Sweep| s.candidates().iter().map(|c| c.coords.clone()).collect::<Vec<_>>();
+        assert_eq!(a.total(), 7);
+        assert_eq!(coords(&a), coords(&b));
+
+        let mut unique = coords(&a);
+        unique.dedup();
+        assert_eq!(unique.len(), 7, "combinaciones repetidas");
+        assert!(coords(&a).windows(2).all(|w| w[0] < w[1]), "no están en orden de rejilla");
+    }
+
+    #[test]
+    fn random_search_with_more_samples_than_grid_runs_everything() {
+        let sweep = Sweep::new(spec(vec![axis(2, "kernel", &[3.0, 5.0], None)], SearchMode::Random { samples: 10, seed: 1 })).unwrap();
+        assert_eq!(sweep.total(), 2);
+    }
+}
@@ -0,0 +1,373 @@
+use opencv::{core, prelude::*};
+use std::fmt;
+use crate::pipelines::step::PipelineStep;
+
+/// Formato de imagen que se propaga paso a paso durante la validación.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImageFormat {
+    pub channels: i32,
+    /// Profundidad OpenCV (`core::CV_8U`, `core::CV_16S`, ...).
+    pub depth: i32,
+    /// La imagen solo contiene 0/255 (salida de Canny o de un umbral).
+    pub binary: bool,
+    /// Los 3 canales son H, S, V en lugar de B, G, R.
+    pub hsv: bool,
+}
+
+impl ImageFormat {
+    /// Formato de un frame de cámara o de `imread` en color.
+    pub fn bgr8() -> Self {
+        Self { channels: 3, depth: core::CV_8U, binary: false, hsv: false }
+    }
+
+    pub fn of(img: &Mat) -> Self {
+        Self { channels: img.channels(), depth: img.depth(), binary: false, hsv: false }
+    }
+
+    fn gray(self) -> Self {
+        Self { channels: 1, binary: false, hsv: false, ..self }
+    }
+}
+
+impl fmt::Display for ImageFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let depth = match self.depth {
+            core::CV_8U => "8U",
+            core::CV_8S => "8S",
+            core::CV_16U => "16U",
+            core::CV_16S => "16S",
+            core::CV_32S => "32S",
+            core::CV_32F => "32F",
+            core::CV_64F => "64F",
+            _ => "?",
+        };
+        write!(f, "{}C{}", depth, self.channels)?;
+        if self.hsv { write!(f, " (HSV)")?; }
+        if self.binary { write!(f, " (binaria)")?; }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    /// Se corrigió la receta automáticamente.
+    Fixed,
+    /// Funciona, pero probablemente no como se espera.
+    Warning,
+    /// El paso fallaría en OpenCV; la receta se rechaza.
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct ValidationIssue {
+    /// Posición (0-based) del paso en la receta original.
+    pub index: usize,
+    pub step: String,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Paso {} ({}): {}", self.index + 1, self.step, self.message)
+    }
+}
+
+/// Resultado de la validación: la receta (ya corregida si se pidió) y los avisos.
+#[derive(Debug, Clone)]
+pub struct Validation {
+    pub steps: Vec<PipelineStep>,
+    pub issues: Vec<ValidationIssue>,
+    /// Formato que producirá el último paso.
+    pub output: ImageFormat,
+}
+
+impl Validation {
+    pub fn is_valid(&self) -> bool {
+        !self.issues.iter().any(|i| i.severity == Severity::Error)
+    }
+}
+
+/// Qué hacer con un paso tras compararlo con el formato actual.
+enum Check {
+    Ok,
+    /// Insertar estos pasos antes del actual.
+    Insert(Vec<PipelineStep>, String),
+    /// Omitir el paso (no tiene efecto sobre este formato).
+    Skip(String),
+    Warn(String),
+    Reject(String),
+}
+
+pub struct RecipeValidator;
+
+impl RecipeValidator {
+    /// Recorre la receta propagando canales y profundidad, antes de procesar ningún frame.
+    /// Los parámetros de cada paso se comprueban también (kernels impares, umbrales, ...).
+    /// Con `auto_fix` inserta la conversión a grises que falte u omite pasos sin efecto;
+    /// sin él, esos casos se reportan como errores.
+    pub fn check(receta: &[PipelineStep], input: ImageFormat, auto_fix: bool) -> Validation {
+        let mut format = input;
+        let mut steps = Vec::with_capacity(receta.len());
+        let mut issues = Vec::new();
+
+        for (index, step) in receta.iter().enumerate() {
+            let mut issue = |severity, message: String| issues.push(ValidationIssue {
+                index,
+                step: step.to_string(),
+                severity,
+                message,
+            });
+
+            match Self::check_step(step, format, index == 0) {
+                Check::Ok => {}
+                Check::Warn(msg) => issue(Severity::Warning, msg),
+                Check::Reject(msg) => issue(Severity::Error, msg),
+                Check::Insert(extra, msg) if auto_fix => {
+                    issue(Severity::Fixed, msg);
+                    for fix in extra {
+                        format = Self::output(&fix, format);
+                        steps.push(fix);
+                    }
+                    // El paso puede seguir teniendo avisos tras la corrección
+                    match Self::check_step(step, format, index == 0) {
+                        Check::Warn(msg) => issue(Severity::Warning, msg),
+                        Check::Reject(msg) | Check::Skip(msg) | Check::Insert(_, msg) => issue(Severity::Error, msg),
+                        Check::Ok => {}
+                    }
+                }
+                Check::Skip(msg) if auto_fix => {
+                    issue(Severity::Fixed, msg);
+                    continue;
+                }
+                Check::Insert(_, msg) | Check::Skip(msg) => issue(Severity::Error, msg),
+            }
+
+            format = Self::output(step, format);
+            steps.push(step.clone());
+        }
+
+        Validation { steps, issues, output: format }
+    }
+
+    /// `first`: el paso es el primero de la receta original.
+    fn check_step(step: &PipelineStep, fmt: ImageFormat, first: bool) -> Check {
+        let to_gray = || vec![PipelineStep::Grayscale];
+
+        if let Err(e) = step.check_params() {
+            return Check::Reject(e.to_string());
+        }
+
+        match step {
+            PipelineStep::Grayscale => match fmt.channels {
+                1 => Check::Skip("la imagen ya tiene 1 canal; la conversión a grises se omite".to_string()),
+                3 | 4 if fmt.hsv => Check::Warn("convierte una imagen HSV como si fuera BGR; usa 'value_channel' para el brillo".to_string()),
+                3 | 4 => Check::Ok,
+                n => Check::Reject(format!("requiere 3 o 4 canales, recibe {}", n)),
+            },
+            PipelineStep::Hsv => match fmt.channels {
+                3 if fmt.hsv => Check::Skip("la imagen ya está en HSV".to_string()),
+                3 => Check::Ok,
+                n => Check::Reject(format!("requiere una imagen BGR de 3 canales, recibe {} (no se puede recuperar el color)", n)),
+            },
+            PipelineStep::ValueChannel => match fmt.channels {
+                3 if fmt.hsv => Check::Ok,
+                3 => Check::Insert(vec![PipelineStep::Hsv], "requiere HSV; se inserta la conversión BGR -> HSV".to_string()),
+                n => Check::Reject(format!("requiere una imagen HSV de 3 canales, recibe {}", n)),
+            },
+            PipelineStep::Clahe { .. } => {
+                if fmt.channels != 1 {
+                    Check::Insert(to_gray(), format!("CLAHE requiere 1 canal, recibe {}; se inserta 'grayscale'", fmt.channels))
+                } else if fmt.depth != core::CV_8U && fmt.depth != core::CV_16U {
+                    Check::Reject(format!("CLAHE requiere 8U o 16U, recibe {}", fmt))
+                } else {
+                    Check::Ok
+                }
+            }
+            PipelineStep::MedianBlur { kernel } if *kernel > 5 && fmt.depth != core::CV_8U => {
+                Check::Reject(format!("la mediana con kernel > 5 requiere 8U, recibe {}", fmt))
+            }
+            PipelineStep::Bilateral { .. } => {
+                if fmt.channels != 1 && fmt.channels != 3 {
+                    Check::Reject(format!("el filtro bilateral requiere 1 o 3 canales, recibe {}", fmt.channels))
+                } else if fmt.depth != core::CV_8U && fmt.depth != core::CV_32F {
+                    Check::Reject(format!("el filtro bilateral requiere 8U o 32F, recibe {}", fmt))
+                } else {
+                    Check::Ok
+                }
+            }
+            PipelineStep::Canny { .. } => {
+                if fmt.depth != core::CV_8U {
+                    Check::Reject(format!("Canny requiere 8U, recibe {}", fmt))
+                } else if fmt.channels != 1 {
+                    Check::Insert(to_gray(), format!("Canny requiere 1 canal, recibe {}; se inserta 'grayscale'", fmt.channels))
+                } else {
+                    Check::Ok
+                }
+            }
+            // El umbral fijo se limita a 0-255 (`Thresholder::check_fixed`): solo tiene sentido en 8U
+            PipelineStep::Threshold { .. } => {
+                if fmt.depth != core::CV_8U {
+                    Check::Reject(format!("el umbral fijo requiere 8U (umbral entre 0 y 255), recibe {}", fmt))
+                } else if fmt.channels != 1 {
+                    Check::Insert(to_gray(), format!("el umbral requiere 1 canal, recibe {}; se inserta 'grayscale'", fmt.channels))
+                } else if fmt.binary {
+                    Check::Warn("la imagen ya es binaria; el umbral no tiene efecto".to_string())
+                } else {
+                    Check::Ok
+                }
+            }
+            PipelineStep::Otsu { .. } | PipelineStep::Triangle { .. }
+            | PipelineStep::AdaptiveMean { .. } | PipelineStep::AdaptiveGaussian { .. } => {
+                if fmt.depth != core::CV_8U {
+                    Check::Reject(format!("'{}' requiere 8U, recibe {}", step.name(), fmt))
+                } else if fmt.channels != 1 {
+                    Check::Insert(to_gray(), format!("'{}' requiere 1 canal, recibe {}; se inserta 'grayscale'", step.name(), fmt.channels))
+                } else if fmt.binary {
+                    Check::Warn("la imagen ya es binaria; el umbral no tiene efecto".to_string())
+                } else {
+                    Check::Ok
+                }
+            }
+            PipelineStep::Morphology { .. } if fmt.depth == core::CV_8S || fmt.depth == core::CV_32S => {
+                Check::Reject(format!("la morfología no admite {}", fmt))
+            }
+            PipelineStep::Morphology { operation, .. } if fmt.hsv => Check::Warn(format!(
+                "'{}' sobre HSV mezcla tono y brillo; conviértela a grises o usa 'value_channel' antes",
+                operation.name()
+            )),
+            // La calibración describe la geometría del frame tal como sale de la cámara
+            PipelineStep::Undistort if !first => {
+                Check::Warn("la corrección de lente debería ser el primer paso de la receta".to_string())
+            }
+            // Sobre una imagen no binaria todo píxel distinto de 0 contaría como objeto
+            PipelineStep::Contours { .. } if !fmt.binary || fmt.depth != core::CV_8U => Check::Reject(format!(
+                "los contornos requieren una imagen binaria 8U, recibe {} (añade Canny o un umbral antes)",
+                fmt
+            )),
+            _ => Check::Ok,
+        }
+    }
+
+    /// Formato que produce `step` a partir de `fmt`.
+    fn output(step: &PipelineStep, fmt: ImageFormat) -> ImageFormat {
+        match step {
+            PipelineStep::Grayscale | PipelineStep::ValueChannel => fmt.gray(),
+            PipelineStep::Hsv => ImageFormat { channels: 3, hsv: true, binary: false, ..fmt },
+            PipelineStep::Normalize | PipelineStep::BackgroundCorrection | PipelineStep::Laplacian => {
+                ImageFormat { depth: core::CV_8U, binary: false, ..fmt }
+            }
+            PipelineStep::Canny { .. } => ImageFormat { channels: 1, depth: core::CV_8U, binary: true, hsv: false },
+            PipelineStep::Threshold { .. }
+            | PipelineStep::Otsu { .. } | PipelineStep::Triangle { .. }
+            | PipelineStep::AdaptiveMean { .. } | PipelineStep::AdaptiveGaussian { .. } => {
+                ImageFormat { channels: 1, depth: core::CV_8U, binary: true, hsv: false }
+            }
+            // Sobre una imagen binaria el resultado sigue siendo 0/255
+            PipelineStep::Morphology { .. } => fmt,
+            // La corrección de lente y las transformaciones afines conservan canales y profundidad,
+            // pero interpolan: una imagen binaria deja de serlo en los bordes de los objetos
+            PipelineStep::Undistort | PipelineStep::Translate { .. } | PipelineStep::Rotate { .. } => {
+                ImageFormat { binary: false, ..fmt }
+            }
+            // El paso de contornos mide sin modificar la imagen (el dibujo va aparte)
+            PipelineStep::Contours { .. } => fmt,
+            _ => ImageFormat { binary: false, ..fmt },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nixvision_lib::filters::contour::{ContourFilter, ContourMode, Selection};
+    use nixvision_lib::filters::morphology::{KernelShape, MorphOp};
+
+    fn contours() -> PipelineStep {
+        PipelineStep::Contours {
+            filter: ContourFilter::min_area(500.0),
+            mode: ContourMode::External,
+            level: None,
+            select: Selection::Largest,
+        }
+    }
+
+    fn gray(depth: i32) -> ImageFormat {
+        ImageFormat { channels: 1, depth, binary: false, hsv: false }
+    }
+
+    #[test]
+    fn canny_on_color_inserts_grayscale() {
+        let canny = PipelineStep::Canny { low: 50.0, high: 150.0 };
+
+        let fixed = RecipeValidator::check(&[canny.clone()], ImageFormat::bgr8(), true);
+        assert!(fixed.is_valid());
+        assert_eq!(fixed.steps, vec![PipelineStep::Grayscale, canny.clone()]);
+        assert_eq!(fixed.issues[0].severity, Severity::Fixed);
+
+        let strict = RecipeValidator::check(&[canny], ImageFormat::bgr8(), false);
+        assert!(!strict.is_valid());
+    }
+
+    #[test]
+    fn clahe_rejects_non_8u() {
+        let clahe = [PipelineStep::Clahe { clip_limit: 2.0, grid: 8 }];
+        assert!(RecipeValidator::check(&clahe, gray(core::CV_8U), true).is_valid());
+        assert!(!RecipeValidator::check(&clahe, gray(core::CV_32F), true).is_valid());
+        assert!(!RecipeValidator::check(&clahe, gray(core::CV_16S), true).is_valid());
+    }
+
+    #[test]
+    fn large_median_rejects_non_8u() {
+        let median = |kernel| [PipelineStep::MedianBlur { kernel }];
+        assert!(RecipeValidator::check(&median(7), gray(core::CV_8U), true).is_valid());
+        assert!(RecipeValidator::check(&median(5), gray(core::CV_32F), true).is_valid());
+        assert!(!RecipeValidator::check(&median(7), gray(core::CV_32F), true).is_valid());
+    }
+
+    #[test]
+    fn fixed_threshold_requires_8u() {
+        let threshold = [PipelineStep::Threshold { value: 127.0, invert: false }];
+        assert!(RecipeValidator::check(&threshold, gray(core::CV_8U), true).is_valid());
+        assert!(!RecipeValidator::check(&threshold, gray(core::CV_32F), true).is_valid());
+    }
+
+    #[test]
+    fn binary_propagates_through_morphology() {
+        let receta = [
+            PipelineStep::Otsu { invert: false },
+            PipelineStep::Morphology { operation: MorphOp::Close, shape: KernelShape::Rect, size: 5, iterations: 1 },
+            contours(),
+        ];
+        let validation = RecipeValidator::check(&receta, ImageFormat::bgr8(), true);
+        assert!(validation.is_valid(), "{:?}", validation.issues);
+        assert_eq!(validation.steps[0], PipelineStep::Grayscale);
+    }
+
+    #[test]
+    fn contours_reject_non_binary_images() {
+        let receta = [PipelineStep::Grayscale, contours()];
+        assert!(!RecipeValidator::check(&receta, ImageFormat::bgr8(), true).is_valid());
+        assert!(!RecipeValidator::check(&[contours()], ImageFormat::bgr8(), true).is_valid());
+    }
+
+    #[test]
+    fn contours_keep_the_binary_image_for_later_steps() {
+        let receta = [PipelineStep::Otsu { invert: false }, contours(), contours()];
+        assert!(RecipeValidator::check(&receta, gray(core::CV_8U), true).is_valid());
+    }
+
+    #[test]
+    fn affine_steps_clear_binary() {
+        let receta = [PipelineStep::Otsu { invert: false }, PipelineStep::Rotate { angle: 10.0 }, contours()];
+        assert!(!RecipeValidator::check(&receta, gray(core::CV_8U), true).is_valid());
+
+        let rethresholded = [
+            PipelineStep::Otsu { invert: false },
+            PipelineStep::Rotate { angle: 10.0 },
+            PipelineStep::Threshold { value: 127.0, invert: false },
+            contours(),
+        ];
+        assert!(RecipeValidator::check(&rethresholded, gray(core::CV_8U), true).is_valid());
+    }
+}
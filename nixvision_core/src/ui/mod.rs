@@ -1,5 +1,7 @@
 use std::io::{self, Write};
 use inquire::Text;
+use crate::pipelines::processing::DetectedObject;
+use crate::pipelines::profiler::TimingStats;
 pub struct Interface;
 
 impl Interface {
@@ -23,6 +25,81 @@ impl Interface {
         println!("\x1b[34m INFO:\x1b[0m {}", msg);
     }
 
+    pub fn warning(msg: &str) {
+        println!("\x1b[33m WARNING:\x1b[0m {}", msg);
+    }
+
+    /// Tabla de métricas del objeto detectado (Punto 5) [cite: 22, 23, 24, 25]
+    pub fn detection(object: &DetectedObject) {
+        let metrics = &object.metrics;
+        println!("✅ Objeto Detectado:");
+        println!("   - Área: {:.2} px", metrics.area);
+        println!("   - Perímetro: {:.2} px", metrics.perimeter);
+        if metrics.holes > 0 {
+            println!("   - Agujeros: {} | Área neta: {:.2} px", metrics.holes, metrics.net_area);
+        }
+        println!("   - Bounding Box: {}x{} px (Ancho x Alto)", metrics.width, metrics.height);
+        println!("   - Centroide: ({:.1}, {:.1}) px", metrics.centroid.x, metrics.centroid.y);
+        println!("   - Circularidad: {:.3} | Relación de aspecto: {:.3}", metrics.circularity, metrics.aspect_ratio);
+        println!("   - Extensión: {:.3} | Solidez: {:.3}", metrics.extent, metrics.solidity);
+        println!("   - Diámetro equivalente: {:.2} px | Orientación: {:.1}°", metrics.equivalent_diameter, metrics.orientation);
+        let rect = metrics.min_rect;
+        println!("   - Rectángulo mínimo: {:.1}x{:.1} px a {:.1}°", rect.size.width, rect.size.height, rect.angle);
+        println!(
+            "   - Círculo mínimo: centro ({:.1}, {:.1}), radio {:.1} px",
+            metrics.enclosing_center.x, metrics.enclosing_center.y, metrics.enclosing_radius
+        );
+        if let Some(e) = metrics.ellipse {
+            println!("   - Elipse: ejes {:.1}x{:.1} px a {:.1}°", e.size.width, e.size.height, e.angle);
+        }
+        if let Some(mm) = &object.physical {
+            println!("📏 Medidas reales:");
+            println!("   - Área: {:.2} mm² | Perímetro: {:.2} mm", mm.area_mm2, mm.perimeter_mm);
+            if metrics.holes > 0 {
+                println!("   - Área neta: {:.2} mm²", mm.net_area_mm2);
+            }
+            println!("   - Bounding Box: {:.2}x{:.2} mm (Ancho x Alto)", mm.width_mm, mm.height_mm);
+            println!("   - Largo x ancho (rectángulo mínimo): {:.2}x{:.2} mm", mm.length_mm, mm.breadth_mm);
+            println!("   - Diámetro equivalente: {:.2} mm", mm.equivalent_diameter_mm);
+        }
+    }
+
+    /// Recuento y tabla resumida de varios objetos detectados.
+    pub fn objects(objects: &[DetectedObject]) {
+        println!("✅ {} objetos detectados:", objects.len());
+        // Con escala calibrada, área, perímetro y caja se muestran en mm
+        let mm = objects.iter().all(|o| o.physical.is_some());
+        let unit = if mm { "mm" } else { "px" };
+        println!(
+            "{:>4} {:>12} {:>12} {:>15} {:>18} {:>8}",
+            "ID", format!("Área {}²", unit), format!("Perím. {}", unit), format!("Caja {}", unit), "Centroide px", "Circ."
+        );
+        println!("{}", "-".repeat(74));
+        for object in objects {
+            let m = &object.metrics;
+            let (area, perimeter, bbox) = match &object.physical {
+                Some(p) if mm => (p.area_mm2, p.perimeter_mm, format!("{:.1}x{:.1}", p.width_mm, p.height_mm)),
+                _ => (m.area, m.perimeter, format!("{}x{}", m.width, m.height)),
+            };
+            println!(
+                "{:>4} {:>12.2} {:>12.2} {:>15} {:>18} {:>8.3}",
+                format!("#{}", object.id),
+                area,
+                perimeter,
+                bbox,
+                format!("({:.1}, {:.1})", m.centroid.x, m.centroid.y),
+                m.circularity
+            );
+        }
+    }
+
+    /// Resumen del recuento de objetos en una secuencia de frames.
+    pub fn object_counts(counts: &[usize]) {
+        let (Some(min), Some(max)) = (counts.iter().min(), counts.iter().max()) else { return };
+        let mean = counts.iter().sum::<usize>() as f64 / counts.len() as f64;
+        println!("🔢 Objetos por frame: mín {} | media {:.1} | máx {}", min, mean, max);
+    }
+
     pub fn progress_bar(current: usize, total: usize) {
         let progress = (current as f64 / total as f64) * 20.0;
         print!("\r[");
@@ -34,6 +111,18 @@ impl Interface {
         io::stdout().flush().unwrap();
     }
 
+    /// Tabla de tiempos por paso (media, p95 y máximo en ms).
+    pub fn timing_table(rows: &[TimingStats]) {
+        println!("{:<28} {:>8} {:>10} {:>10} {:>10}", "Paso", "Muestras", "Media ms", "p95 ms", "Máx ms");
+        println!("{}", "-".repeat(70));
+        for row in rows {
+            println!(
+                "{:<28} {:>8} {:>10.2} {:>10.2} {:>10.2}",
+                row.label, row.samples, row.mean_ms, row.p95_ms, row.max_ms
+            );
+        }
+    }
+
     pub fn ask_text(prompt: &str, default: &str) -> String {
         Text::new(prompt)
             .with_default(default)
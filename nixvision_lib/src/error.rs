@@ -0,0 +1,59 @@
+use std::fmt;
+use opencv::core;
+
+/// Errores de la librería de filtros de NixVision.
+#[derive(Debug)]
+pub enum NixError {
+    /// Un parámetro de filtro fuera de rango (kernel par, umbrales invertidos, etc.).
+    InvalidParameter {
+        filter: &'static str,
+        param: &'static str,
+        reason: String,
+    },
+    /// Error devuelto por OpenCV.
+    OpenCv(opencv::Error),
+}
+
+pub type NixResult<T> = std::result::Result<T, NixError>;
+
+impl NixError {
+    pub fn invalid(filter: &'static str, param: &'static str, reason: impl Into<String>) -> Self {
+        NixError::InvalidParameter { filter, param, reason: reason.into() }
+    }
+}
+
+impl fmt::Display for NixError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NixError::InvalidParameter { filter, param, reason } => {
+                write!(f, "{}: parámetro '{}' inválido: {}", filter, param, reason)
+            }
+            NixError::OpenCv(e) => write!(f, "OpenCV: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for NixError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            NixError::OpenCv(e) => Some(e),
+            NixError::InvalidParameter { .. } => None,
+        }
+    }
+}
+
+impl From<opencv::Error> for NixError {
+    fn from(e: opencv::Error) -> Self {
+        NixError::OpenCv(e)
+    }
+}
+
+/// Permite usar `?` con filtros de la librería dentro de código que devuelve `opencv::Result`.
+impl From<NixError> for opencv::Error {
+    fn from(e: NixError) -> Self {
+        match e {
+            NixError::OpenCv(e) => e,
+            other => opencv::Error::new(core::StsBadArg, other.to_string()),
+        }
+    }
+}
\ No newline at end of file
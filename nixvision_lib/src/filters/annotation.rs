@@ -0,0 +1,198 @@
+use opencv::{
+    core::{Mat, Point, Point2f, Scalar, Vector},
+    imgproc,
+    prelude::*,
+    Result
+};
+use serde::{Serialize, Deserialize};
+use crate::filters::contour::ContourMetrics;
+use crate::filters::scale::Scale;
+
+/// Una capa del dibujo: si se pinta, con qué color (B, G, R) y grosor en píxeles.
+/// Dentro de `AnnotationStyle` basta con indicar los campos que cambian.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Layer {
+    pub show: bool,
+    pub color: [u8; 3],
+    pub thickness: i32,
+}
+
+impl Layer {
+    pub const fn new(show: bool, color: [u8; 3], thickness: i32) -> Self {
+        Self { show, color, thickness }
+    }
+
+    fn scalar(&self) -> Scalar {
+        Scalar::new(self.color[0] as f64, self.color[1] as f64, self.color[2] as f64, 0.0)
+    }
+}
+
+/// Estilo de las anotaciones sobre los objetos detectados (sección `[annotation]`).
+/// Por defecto reproduce el dibujo clásico: contorno verde, agujeros azules,
+/// bounding box rojo de 5 px y el ID del objeto. Los campos que falten, también dentro
+/// de una capa (`[annotation.bbox] color = [255, 0, 0]`), toman el valor por defecto.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(from = "StylePatch")]
+pub struct AnnotationStyle {
+    pub contour: Layer,
+    pub holes: Layer,
+    pub bbox: Layer,
+    /// Rectángulo rotado de área mínima.
+    pub min_rect: Layer,
+    /// Punto en el centroide (`thickness` es el radio).
+    pub centroid: Layer,
+    /// Texto junto al bounding box (color y grosor de la fuente).
+    pub label: Layer,
+    /// Rotular el ID (`#1`, `#2`...).
+    pub ids: bool,
+    /// Rotular área y tamaño del bounding box (`1234 px2 40x31`, o en mm con escala).
+    pub metrics: bool,
+    pub font_scale: f64,
+}
+
+impl Default for AnnotationStyle {
+    fn default() -> Self {
+        Self {
+            contour: Layer::new(true, [0, 255, 0], 2),
+            holes: Layer::new(true, [255, 0, 0], 2),
+            bbox: Layer::new(true, [0, 0, 255], 5),
+            min_rect: Layer::new(false, [255, 255, 0], 2),
+            centroid: Layer::new(false, [0, 255, 255], 4),
+            label: Layer::new(true, [0, 0, 255], 2),
+            ids: true,
+            metrics: false,
+            font_scale: 1.0,
+        }
+    }
+}
+
+/// Capa parcial tal como se escribe en la configuración.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct LayerPatch {
+    show: Option<bool>,
+    color: Option<[u8; 3]>,
+    thickness: Option<i32>,
+}
+
+impl LayerPatch {
+    fn over(self, base: Layer) -> Layer {
+        Layer {
+            show: self.show.unwrap_or(base.show),
+            color: self.color.unwrap_or(base.color),
+            thickness: self.thickness.unwrap_or(base.thickness),
+        }
+    }
+}
+
+/// `AnnotationStyle` parcial: lo indicado se aplica sobre el estilo por defecto.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct StylePatch {
+    contour: LayerPatch,
+    holes: LayerPatch,
+    bbox: LayerPatch,
+    min_rect: LayerPatch,
+    centroid: LayerPatch,
+    label: LayerPatch,
+    ids: Option<bool>,
+    metrics: Option<bool>,
+    font_scale: Option<f64>,
+}
+
+impl From<StylePatch> for AnnotationStyle {
+    fn from(patch: StylePatch) -> Self {
+        let base = Self::default();
+        Self {
+            contour: patch.contour.over(base.contour),
+            holes: patch.holes.over(base.holes),
+            bbox: patch.bbox.over(base.bbox),
+            min_rect: patch.min_rect.over(base.min_rect),
+            centroid: patch.centroid.over(base.centroid),
+            label: patch.label.over(base.label),
+            ids: patch.ids.unwrap_or(base.ids),
+            metrics: patch.metrics.unwrap_or(base.metrics),
+            font_scale: patch.font_scale.unwrap_or(base.font_scale),
+        }
+    }
+}
+
+pub struct Annotator; // Capa de dibujo: las mediciones de NixContour no tocan ninguna imagen
+
+impl Annotator {
+    /// Dibuja el objeto `id` sobre `image` (BGR) según `style`; con `scale` las medidas se rotulan en mm.
+    pub fn draw(image: &mut Mat, id: usize, target: &ContourMetrics, scale: Option<&Scale>, style: &AnnotationStyle) -> Result<()> {
+        if style.contour.show {
+            Self::polygon(image, &target.points, &style.contour)?;
+        }
+        if style.holes.show {
+            for hole in &target.hole_points {
+                Self::polygon(image, hole, &style.holes)?;
+            }
+        }
+        if style.bbox.show {
+            imgproc::rectangle(image, target.bbox, style.bbox.scalar(), style.bbox.thickness, imgproc::LINE_8, 0)?;
+        }
+        if style.min_rect.show {
+            let mut corners = [Point2f::default(); 4];
+            target.min_rect.points(&mut corners)?;
+            let corners: Vec<Point> = corners.iter().map(|p| Point::new(p.x.round() as i32, p.y.round() as i32)).collect();
+            Self::polygon(image, &corners, &style.min_rect)?;
+        }
+        if style.centroid.show {
+            let center = Point::new(target.centroid.x.round() as i32, target.centroid.y.round() as i32);
+            imgproc::circle(image, center, style.centroid.thickness, style.centroid.scalar(), imgproc::FILLED, imgproc::LINE_AA, 0)?;
+        }
+        if style.label.show {
+            Self::label(image, id, target, scale, style)?;
+        }
+        Ok(())
+    }
+
+    fn polygon(image: &mut Mat, points: &[Point], layer: &Layer) -> Result<()> {
+        let contour = Vector::<Point>::from_slice(points);
+        imgproc::polylines(image, &contour, true, layer.scalar(), layer.thickness, imgproc::LINE_8, 0)
+    }
+
+    /// Una línea por dato, sobre la esquina superior izquierda del bounding box
+    /// (dentro de él si no cabe por encima).
+    fn label(image: &mut Mat, id: usize, target: &ContourMetrics, scale: Option<&Scale>, style: &AnnotationStyle) -> Result<()> {
+        let mut lines = Vec::new();
+        if style.ids {
+            lines.push(format!("#{}", id));
+        }
+        if style.metrics {
+            lines.push(match scale {
+                Some(scale) => {
+                    let m = scale.measure(target);
+                    format!("{:.1} mm2 {:.1}x{:.1} mm", m.area_mm2, m.width_mm, m.height_mm)
+                }
+                None => format!("{:.0} px2 {}x{}", target.area, target.width, target.height),
+            });
+        }
+        if lines.is_empty() {
+            return Ok(());
+        }
+
+        let mut baseline = 0;
+        let text_size = imgproc::get_text_size("#0", imgproc::FONT_HERSHEY_SIMPLEX, style.font_scale, style.label.thickness, &mut baseline)?;
+        let line_height = text_size.height + baseline + 4;
+        let block = line_height * lines.len() as i32;
+        let top = if target.bbox.y >= block { target.bbox.y - block } else { target.bbox.y };
+
+        for (i, line) in lines.iter().enumerate() {
+            imgproc::put_text(
+                image,
+                line,
+                Point::new(target.bbox.x, top + line_height * (i as i32 + 1) - baseline),
+                imgproc::FONT_HERSHEY_SIMPLEX,
+                style.font_scale,
+                style.label.scalar(),
+                style.label.thickness,
+                imgproc::LINE_AA,
+                false,
+            )?;
+        }
+        Ok(())
+    }
+}
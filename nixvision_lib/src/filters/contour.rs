@@ -1,78 +1,318 @@
 use opencv::{
-    core::{Vector, Point, Scalar, Mat, Rect},
+    core::{Vector, Point, Point2f, Mat, Rect, RotatedRect, Size, Vec4i},
     imgproc,
+    prelude::*,
     Result
 };
+use serde::{Serialize, Deserialize};
+use crate::error::{NixError, NixResult};
+use std::f64::consts::PI;
 
+/// Qué contornos recupera `find_and_measure`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ContourMode {
+    /// Solo los contornos exteriores: un objeto con agujeros se mide como sólido.
+    #[default]
+    External,
+    /// Árbol completo: cada objeto informa de sus agujeros y de su área neta.
+    /// Conviene usarlo sobre una imagen umbralizada (regiones rellenas); sobre bordes de
+    /// Canny cada trazo genera un contorno interior y otro exterior.
+    Tree,
+}
+
+/// Criterios que debe cumplir un contorno para considerarse objeto.
+/// Los límites ausentes no se comprueban; `min_area` conserva el filtro histórico (`area > min_area`).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct ContourFilter {
+    pub min_area: f64,
+    pub max_area: Option<f64>,
+    pub min_perimeter: Option<f64>,
+    pub max_perimeter: Option<f64>,
+    pub min_circularity: Option<f64>,
+    pub max_circularity: Option<f64>,
+    pub min_aspect_ratio: Option<f64>,
+    pub max_aspect_ratio: Option<f64>,
+    pub min_solidity: Option<f64>,
+    /// Descarta los contornos que tocan el borde de la imagen (objetos cortados).
+    pub exclude_border: bool,
+}
+
+impl ContourFilter {
+    /// Filtro equivalente al antiguo `area > min_area`.
+    pub fn min_area(min_area: f64) -> Self {
+        Self { min_area, ..Self::default() }
+    }
+
+    /// Cada rango debe tener `min <= max`.
+    pub fn check(&self) -> NixResult<()> {
+        let ranges = [
+            ("max_area", Some(self.min_area), self.max_area),
+            ("max_perimeter", self.min_perimeter, self.max_perimeter),
+            ("max_circularity", self.min_circularity, self.max_circularity),
+            ("max_aspect_ratio", self.min_aspect_ratio, self.max_aspect_ratio),
+        ];
+        for (param, min, max) in ranges {
+            if let (Some(min), Some(max)) = (min, max)
+                && min > max
+            {
+                return Err(NixError::invalid("contours", param, format!("debe ser mayor o igual que el mínimo ({} < {})", max, min)));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn accepts(&self, m: &ContourMetrics, image: Size) -> bool {
+        let within = |value: f64, min: Option<f64>, max: Option<f64>| {
+            min.is_none_or(|min| value >= min) && max.is_none_or(|max| value <= max)
+        };
+        let touches_border = m.bbox.x <= 0
+            || m.bbox.y <= 0
+            || m.bbox.x + m.bbox.width >= image.width
+            || m.bbox.y + m.bbox.height >= image.height;
+
+        m.area > self.min_area
+            && within(m.area, None, self.max_area)
+            && within(m.perimeter, self.min_perimeter, self.max_perimeter)
+            && within(m.circularity, self.min_circularity, self.max_circularity)
+            && within(m.aspect_ratio, self.min_aspect_ratio, self.max_aspect_ratio)
+            && within(m.solidity, self.min_solidity, None)
+            && !(self.exclude_border && touches_border)
+    }
+}
+
+/// Qué objetos se conservan de los que pasan el filtro.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Selection {
+    /// El de mayor área (comportamiento clásico).
+    #[default]
+    Largest,
+    /// Los `k` de mayor área.
+    KLargest(usize),
+    /// Todos, de mayor a menor área.
+    All,
+    /// El de centroide más cercano al centro de la imagen.
+    ClosestToCenter,
+}
+
+impl Selection {
+    pub fn check(self) -> NixResult<()> {
+        match self {
+            Selection::KLargest(0) => Err(NixError::invalid("contours", "k_largest", "debe ser al menos 1")),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct ContourMetrics {
     pub area: f64,
     pub perimeter: f64,
     pub bbox: Rect,
     pub width: i32,  // Añadido para Punto 5 [cite: 25]
     pub height: i32, // Añadido para Punto 5 [cite: 25]
+    /// Centro de masa (momentos m10/m00, m01/m00); el centro del bbox si el área es 0.
+    pub centroid: Point2f,
+    /// 4π·área / perímetro² (1.0 = círculo perfecto, tiende a 0 en formas alargadas o irregulares).
+    pub circularity: f64,
+    /// Ancho / alto del bounding box.
+    pub aspect_ratio: f64,
+    /// Área del contorno / área del bounding box.
+    pub extent: f64,
+    /// Área del contorno / área de su envolvente convexa (1.0 = objeto convexo y compacto).
+    pub solidity: f64,
+    /// Diámetro del círculo con la misma área.
+    pub equivalent_diameter: f64,
+    /// Ángulo del eje principal en grados (-90, 90], a partir de los momentos centrales.
+    pub orientation: f64,
+    /// Rectángulo rotado de área mínima.
+    pub min_rect: RotatedRect,
+    /// Círculo mínimo que encierra el contorno (centro y radio).
+    pub enclosing_center: Point2f,
+    pub enclosing_radius: f32,
+    /// Elipse ajustada por mínimos cuadrados (requiere al menos 5 puntos).
+    pub ellipse: Option<RotatedRect>,
+    /// Profundidad en el árbol de contornos (0 = exterior; 1 = agujero; 2 = isla dentro de un agujero...).
+    pub level: i32,
+    /// Índice del contorno padre, si lo tiene.
+    pub parent: Option<i32>,
+    /// Agujeros directos del objeto (hijos en el árbol).
+    pub holes: usize,
+    /// Área menos el área de sus agujeros (igual a `area` sin agujeros o en modo `External`).
+    pub net_area: f64,
     pub index: i32,
+    /// Puntos del contorno (aproximación simple), para dibujarlo sin volver a buscarlo.
+    pub points: Vec<Point>,
+    /// Puntos de cada agujero directo (vacío en modo `External`).
+    pub hole_points: Vec<Vec<Point>>,
 }
 
 pub struct NixContour;
 
 impl NixContour {
-    /// Detecta y mide contornos filtrando por un área mínima[cite: 5, 15, 26].
+    /// Detecta y mide los contornos que cumplen `filter`[cite: 5, 15, 26].
+    /// En modo `Tree` se devuelven los contornos exteriores de cada objeto (niveles pares)
+    /// con sus agujeros contados; `level` restringe el resultado a una profundidad concreta
+    /// (p. ej. `Some(1)` mide los propios agujeros).
+    /// Solo mide: el dibujo corresponde a `annotation::Annotator`.
     pub fn find_and_measure(
         edges: &Mat, 
-        original: &mut Mat, 
-        min_area: f64 
+        filter: &ContourFilter,
+        mode: ContourMode,
+        level: Option<i32>,
     ) -> Result<Vec<ContourMetrics>> {
         let mut contours = Vector::<Vector<Point>>::new();
+        let mut hierarchy = Vector::<Vec4i>::new(); // [siguiente, anterior, primer hijo, padre]
         let mut results = Vec::new();
+        let size = edges.size()?;
 
-        // 1. Encontrar contornos externos [cite: 14, 21]
-        imgproc::find_contours(
+        // 1. Encontrar contornos (externos o el árbol completo) [cite: 14, 21]
+        let retrieval = match mode {
+            ContourMode::External => imgproc::RETR_EXTERNAL,
+            ContourMode::Tree => imgproc::RETR_TREE,
+        };
+        imgproc::find_contours_with_hierarchy(
             edges, 
             &mut contours, 
-            imgproc::RETR_EXTERNAL, 
+            &mut hierarchy,
+            retrieval, 
             imgproc::CHAIN_APPROX_SIMPLE, 
             Point::new(0, 0)
         )?;
 
         for i in 0..contours.len() {
+            let depth = Self::depth(&hierarchy, i)?;
+            let wanted = match level {
+                Some(level) => depth == level,
+                None => depth % 2 == 0,
+            };
+            if !wanted {
+                continue;
+            }
+
             let cnt = contours.get(i)?;
-            let area = imgproc::contour_area(&cnt, false)?;
-
-            // 2. Filtrado para evitar ruido (Paso 6) [cite: 15, 26]
-            if area > min_area { 
-                let perimeter = imgproc::arc_length(&cnt, true)?; 
-                let bbox = imgproc::bounding_rect(&cnt)?; // Cálculo de Bounding Box [cite: 25]
-                
-                // 3. Visualización de contornos detectados en Verde [cite: 21]
-                imgproc::draw_contours(
-                    original, &contours, i as i32, 
-                    Scalar::new(0.0, 255.0, 0.0, 0.0), 2, 8, &Mat::default(), 0, Point::new(0, 0)
-                )?;
-
-                // 4. Almacenar métricas completas (Punto 5) 
-                results.push(ContourMetrics { 
-                    area, 
-                    perimeter, 
-                    bbox, 
-                    width: bbox.width,   // Extraemos el ancho
-                    height: bbox.height, // Extraemos el alto
-                    index: i as i32 
-                });
+            // Descarte rápido del ruido antes de calcular todos los descriptores (Paso 6) [cite: 15, 26]
+            if imgproc::contour_area(&cnt, false)? <= filter.min_area {
+                continue;
+            }
+
+            // 2. Métricas completas (Punto 5) y agujeros
+            let mut metrics = Self::measure(&cnt, i as i32)?;
+            metrics.level = depth;
+            let parent = hierarchy.get(i)?[3];
+            metrics.parent = (parent >= 0).then_some(parent);
+
+            let children = Self::children(&hierarchy, i)?;
+            let mut hole_area = 0.0;
+            for &child in &children {
+                let hole = contours.get(child)?;
+                hole_area += imgproc::contour_area(&hole, false)?;
+                metrics.hole_points.push(hole.to_vec());
+            }
+            metrics.holes = children.len();
+            metrics.net_area = metrics.area - hole_area;
+
+            if filter.accepts(&metrics, size) {
+                results.push(metrics);
             }
         }
         Ok(results)
     }
 
-    /// Resalta el contorno más grande con un rectángulo rojo (Paso 7).
-    pub fn draw_highlight(original: &mut Mat, target: &ContourMetrics) -> Result<()> {
-        imgproc::rectangle(
-            original, 
-            target.bbox, 
-            Scalar::new(0.0, 0.0, 255.0, 0.0), // Rojo 
-            5, // Grosor aumentado para visibilidad
-            imgproc::LINE_8, 
-            0
-        )?;
-        Ok(())
+    /// Aplica la estrategia de selección; el primer elemento es el objeto principal.
+    pub fn select(mut objects: Vec<ContourMetrics>, selection: Selection, image: Size) -> Vec<ContourMetrics> {
+        objects.sort_by(|a, b| b.area.total_cmp(&a.area));
+        match selection {
+            Selection::Largest => objects.truncate(1),
+            Selection::KLargest(k) => objects.truncate(k),
+            Selection::All => {}
+            Selection::ClosestToCenter => {
+                let (cx, cy) = (image.width as f32 / 2.0, image.height as f32 / 2.0);
+                let distance = |m: &ContourMetrics| (m.centroid.x - cx).hypot(m.centroid.y - cy);
+                objects = objects
+                    .into_iter()
+                    .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+                    .into_iter()
+                    .collect();
+            }
+        }
+        objects
+    }
+
+    /// Profundidad del contorno `i` siguiendo la cadena de padres.
+    fn depth(hierarchy: &Vector<Vec4i>, i: usize) -> Result<i32> {
+        let mut depth = 0;
+        let mut parent = hierarchy.get(i)?[3];
+        while parent >= 0 {
+            depth += 1;
+            parent = hierarchy.get(parent as usize)?[3];
+        }
+        Ok(depth)
     }
-}
\ No newline at end of file
+
+    /// Hijos directos del contorno `i` (sus agujeros).
+    fn children(hierarchy: &Vector<Vec4i>, i: usize) -> Result<Vec<usize>> {
+        let mut children = Vec::new();
+        let mut child = hierarchy.get(i)?[2];
+        while child >= 0 {
+            children.push(child as usize);
+            child = hierarchy.get(child as usize)?[0];
+        }
+        Ok(children)
+    }
+
+    /// Calcula todos los descriptores de forma de un contorno.
+    pub fn measure(cnt: &Vector<Point>, index: i32) -> Result<ContourMetrics> {
+        let area = imgproc::contour_area(cnt, false)?;
+        let perimeter = imgproc::arc_length(cnt, true)?;
+        let bbox = imgproc::bounding_rect(cnt)?; // Cálculo de Bounding Box [cite: 25]
+
+        // Centroide y orientación a partir de los momentos
+        let m = imgproc::moments(cnt, false)?;
+        let centroid = if m.m00 != 0.0 {
+            Point2f::new((m.m10 / m.m00) as f32, (m.m01 / m.m00) as f32)
+        } else {
+            Point2f::new(bbox.x as f32 + bbox.width as f32 / 2.0, bbox.y as f32 + bbox.height as f32 / 2.0)
+        };
+        let orientation = (0.5 * (2.0 * m.mu11).atan2(m.mu20 - m.mu02)).to_degrees();
+
+        let mut hull = Vector::<Point>::new();
+        imgproc::convex_hull(cnt, &mut hull, false, true)?;
+        let hull_area = imgproc::contour_area(&hull, false)?;
+
+        let mut enclosing_center = Point2f::default();
+        let mut enclosing_radius = 0.0f32;
+        imgproc::min_enclosing_circle(cnt, &mut enclosing_center, &mut enclosing_radius)?;
+
+        let ellipse = if cnt.len() >= 5 { Some(imgproc::fit_ellipse(cnt)?) } else { None };
+        let bbox_area = (bbox.width * bbox.height) as f64;
+
+        Ok(ContourMetrics {
+            area,
+            perimeter,
+            bbox,
+            width: bbox.width,   // Extraemos el ancho
+            height: bbox.height, // Extraemos el alto
+            centroid,
+            circularity: if perimeter > 0.0 { 4.0 * PI * area / (perimeter * perimeter) } else { 0.0 },
+            aspect_ratio: if bbox.height > 0 { bbox.width as f64 / bbox.height as f64 } else { 0.0 },
+            extent: if bbox_area > 0.0 { area / bbox_area } else { 0.0 },
+            solidity: if hull_area > 0.0 { area / hull_area } else { 0.0 },
+            equivalent_diameter: (4.0 * area / PI).sqrt(),
+            orientation,
+            min_rect: imgproc::min_area_rect(cnt)?,
+            enclosing_center,
+            enclosing_radius,
+            ellipse,
+            level: 0,
+            parent: None,
+            holes: 0,
+            net_area: area,
+            index,
+            points: cnt.to_vec(),
+            hole_points: Vec::new(),
+        })
+    }
+}
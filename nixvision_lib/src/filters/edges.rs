@@ -1,4 +1,5 @@
 use opencv::{imgproc, prelude::*, core, Result}; // Importamos herramientas de procesamiento, núcleos y manejo de errores
+use crate::error::{NixError, NixResult};
 
 pub struct EdgeManager; // Estructura para agrupar las utilidades de realce y bordes
 
@@ -54,12 +55,26 @@ impl EdgeManager {
         Ok(abs_dst) // Retorna la imagen de bordes
     }
 
-    pub fn canny(src: &Mat, low_threshold: f64, high_threshold: f64) -> Result<Mat> {
+    pub fn canny(src: &Mat, low_threshold: f64, high_threshold: f64) -> NixResult<Mat> {
+        Self::check_canny(low_threshold, high_threshold)?;
         let mut edges = Mat::default();
         // Canny requiere una imagen en escala de grises[cite: 264].
         imgproc::canny(src, &mut edges, low_threshold, high_threshold, 3, false)?;
         Ok(edges)
     }
 
-
+    /// Umbrales no negativos y con low < high (si no, Canny invierte la histéresis).
+    pub fn check_canny(low_threshold: f64, high_threshold: f64) -> NixResult<()> {
+        if low_threshold < 0.0 {
+            return Err(NixError::invalid("canny", "low_threshold", format!("no puede ser negativo, se recibió {}", low_threshold)));
+        }
+        if low_threshold >= high_threshold {
+            return Err(NixError::invalid(
+                "canny",
+                "high_threshold",
+                format!("debe ser mayor que low ({} >= {})", low_threshold, high_threshold),
+            ));
+        }
+        Ok(())
+    }
 }
\ No newline at end of file
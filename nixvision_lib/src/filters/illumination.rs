@@ -1,4 +1,5 @@
 use opencv::{imgproc, prelude::*, core, Result};
+use crate::error::{NixError, NixResult};
 
 pub struct IlluminationManager;
 
@@ -56,7 +57,8 @@ impl IlluminationManager {
     /// OPCIÓN 3: CLAHE (Contrast Limited Adaptive Histogram Equalization) 
     /// Divide la imagen en rejillas y ecualiza localmente. 
     /// Es la opción más robusta para el Escenario 3 (Iluminación no uniforme).
-    pub fn apply_clahe(src: &Mat, clip_limit: f64, grid_size: core::Size) -> Result<Mat> {
+    pub fn apply_clahe(src: &Mat, clip_limit: f64, grid_size: core::Size) -> NixResult<Mat> {
+        Self::check_clahe(clip_limit, grid_size)?;
         let mut dst = Mat::default();
         
         // create_clahe crea el motor del algoritmo.
@@ -69,4 +71,19 @@ impl IlluminationManager {
         
         Ok(dst)
     }
+
+    /// Clip limit positivo y rejilla de al menos 1x1.
+    pub fn check_clahe(clip_limit: f64, grid_size: core::Size) -> NixResult<()> {
+        if clip_limit <= 0.0 {
+            return Err(NixError::invalid("clahe", "clip_limit", format!("debe ser mayor que 0, se recibió {}", clip_limit)));
+        }
+        if grid_size.width < 1 || grid_size.height < 1 {
+            return Err(NixError::invalid(
+                "clahe",
+                "grid_size",
+                format!("debe ser al menos 1x1, se recibió {}x{}", grid_size.width, grid_size.height),
+            ));
+        }
+        Ok(())
+    }
 }
\ No newline at end of file
@@ -0,0 +1,175 @@
+use opencv::{
+    calib3d,
+    core::{self, Mat, Point2f, Point3f, Size, TermCriteria, Vector},
+    imgproc,
+    prelude::*,
+    Result
+};
+use serde::{Serialize, Deserialize};
+use crate::error::{NixError, NixResult};
+
+/// Mínimo de capturas válidas del tablero para una calibración fiable.
+pub const MIN_VIEWS: usize = 5;
+
+/// Parámetros intrínsecos de una cámara y su distorsión de lente.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Intrinsics {
+    /// Matriz de cámara 3x3 por filas: `[fx, 0, cx, 0, fy, cy, 0, 0, 1]`.
+    pub camera_matrix: [f64; 9],
+    /// Coeficientes de distorsión `k1, k2, p1, p2, k3`.
+    pub distortion: Vec<f64>,
+    /// Resolución de las capturas de calibración; solo vale para imágenes de ese tamaño.
+    pub width: i32,
+    pub height: i32,
+    /// Error de reproyección RMS en píxeles (por debajo de ~1 px es una buena calibración).
+    pub rms: f64,
+}
+
+/// Resultado de `LensCalibration::calibrate`.
+#[derive(Debug, Clone)]
+pub struct LensReport {
+    pub intrinsics: Intrinsics,
+    /// Capturas en las que se encontró el tablero (índices de `images`).
+    pub used: Vec<usize>,
+}
+
+pub struct LensCalibration; // Calibración con tablero de ajedrez y corrección de distorsión
+
+impl LensCalibration {
+    /// Esquinas interiores del tablero (`pattern` = esquinas por fila x por columna),
+    /// refinadas a nivel subpíxel; `None` si no aparece completo.
+    pub fn find_corners(img: &Mat, pattern: Size) -> Result<Option<Vector<Point2f>>> {
+        let gray = if img.channels() == 1 {
+            img.clone()
+        } else {
+            let mut gray = Mat::default();
+            imgproc::cvt_color_def(img, &mut gray, imgproc::COLOR_BGR2GRAY)?;
+            gray
+        };
+
+        let mut corners = Vector::<Point2f>::new();
+        let found = calib3d::find_chessboard_corners(
+            &gray,
+            pattern,
+            &mut corners,
+            calib3d::CALIB_CB_ADAPTIVE_THRESH | calib3d::CALIB_CB_NORMALIZE_IMAGE,
+        )?;
+        if !found {
+            return Ok(None);
+        }
+
+        imgproc::corner_sub_pix(
+            &gray,
+            &mut corners,
+            Size::new(11, 11),
+            Size::new(-1, -1),
+            TermCriteria::new(core::TermCriteria_EPS + core::TermCriteria_COUNT, 30, 0.001)?,
+        )?;
+        Ok(Some(corners))
+    }
+
+    /// Calcula intrínsecos y distorsión a partir de capturas de un tablero con `pattern`
+    /// esquinas interiores y casillas de `square_mm`. Todas las capturas deben tener el mismo tamaño.
+    pub fn calibrate(images: &[Mat], pattern: Size, square_mm: f64) -> NixResult<LensReport> {
+        if pattern.width < 2 || pattern.height < 2 {
+            return Err(NixError::invalid(
+                "lens_calibration",
+                "pattern",
+                format!("se necesitan al menos 2x2 esquinas interiores, se recibió {}x{}", pattern.width, pattern.height),
+            ));
+        }
+        if square_mm.is_nan() || square_mm <= 0.0 {
+            return Err(NixError::invalid("lens_calibration", "square_mm", format!("debe ser mayor que 0, se recibió {}", square_mm)));
+        }
+
+        // Esquinas del tablero en el plano Z = 0, en mm
+        let board: Vector<Point3f> = (0..pattern.height)
+            .flat_map(|row| (0..pattern.width).map(move |col| (row, col)))
+            .map(|(row, col)| Point3f::new(col as f32 * square_mm as f32, row as f32 * square_mm as f32, 0.0))
+            .collect();
+
+        let mut object_points = Vector::<Vector<Point3f>>::new();
+        let mut image_points = Vector::<Vector<Point2f>>::new();
+        let mut used = Vec::new();
+        let mut size: Option<Size> = None;
+
+        for (i, img) in images.iter().enumerate() {
+            let img_size = img.size()?;
+            if size.is_some_and(|s| s != img_size) {
+                return Err(NixError::invalid(
+                    "lens_calibration",
+                    "images",
+                    format!("la captura {} mide {}x{}, distinta de las anteriores", i, img_size.width, img_size.height),
+                ));
+            }
+            size = Some(img_size);
+
+            if let Some(corners) = Self::find_corners(img, pattern)? {
+                object_points.push(board.clone());
+                image_points.push(corners);
+                used.push(i);
+            }
+        }
+
+        if used.len() < MIN_VIEWS {
+            return Err(NixError::invalid(
+                "lens_calibration",
+                "images",
+                format!("el tablero solo se encontró en {} capturas; se necesitan al menos {}", used.len(), MIN_VIEWS),
+            ));
+        }
+        let size = size.unwrap_or_default();
+
+        let mut camera_matrix = Mat::default();
+        let mut distortion = Mat::default();
+        let mut rvecs = Vector::<Mat>::new();
+        let mut tvecs = Vector::<Mat>::new();
+        let rms = calib3d::calibrate_camera(
+            &object_points,
+            &image_points,
+            size,
+            &mut camera_matrix,
+            &mut distortion,
+            &mut rvecs,
+            &mut tvecs,
+            0,
+            TermCriteria::new(core::TermCriteria_EPS + core::TermCriteria_COUNT, 30, f64::EPSILON)?,
+        )?;
+
+        let matrix = camera_matrix.data_typed::<f64>()?;
+        let mut intrinsics_matrix = [0.0; 9];
+        intrinsics_matrix.copy_from_slice(&matrix[..9]);
+
+        Ok(LensReport {
+            intrinsics: Intrinsics {
+                camera_matrix: intrinsics_matrix,
+                distortion: distortion.data_typed::<f64>()?.to_vec(),
+                width: size.width,
+                height: size.height,
+                rms,
+            },
+            used,
+        })
+    }
+
+    /// Corrige la distorsión de lente de `src` con la calibración de la cámara.
+    pub fn undistort(src: &Mat, intrinsics: &Intrinsics) -> NixResult<Mat> {
+        let size = src.size()?;
+        if size.width != intrinsics.width || size.height != intrinsics.height {
+            return Err(NixError::invalid(
+                "undistort",
+                "image",
+                format!(
+                    "la calibración es para {}x{} y la imagen mide {}x{}",
+                    intrinsics.width, intrinsics.height, size.width, size.height
+                ),
+            ));
+        }
+
+        let camera_matrix = Mat::from_slice_rows_cols(&intrinsics.camera_matrix, 3, 3)?;
+        let distortion = Mat::from_slice(&intrinsics.distortion)?.try_clone()?;
+        let mut dst = Mat::default();
+        calib3d::undistort_def(src, &mut dst, &camera_matrix, &distortion)?;
+        Ok(dst)
+    }
+}
@@ -6,4 +6,9 @@ pub mod illumination;
 pub mod noise;
 pub mod edges;
 pub mod metrics;
-pub mod contour;
\ No newline at end of file
+pub mod contour;
+pub mod threshold;
+pub mod morphology;
+pub mod annotation;
+pub mod scale;
+pub mod lens;
\ No newline at end of file
@@ -0,0 +1,116 @@
+use opencv::{core, imgproc, prelude::*};
+use serde::{Serialize, Deserialize};
+use crate::error::{NixError, NixResult};
+
+/// Operación morfológica.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MorphOp {
+    /// Adelgaza las regiones blancas y elimina puntos aislados.
+    Erode,
+    /// Engorda las regiones blancas y une bordes cercanos.
+    Dilate,
+    /// Erosión + dilatación: elimina ruido pequeño sin cambiar el tamaño del objeto.
+    Open,
+    /// Dilatación + erosión: cierra huecos y bordes rotos (típico tras Canny).
+    Close,
+    /// Dilatación - erosión: contorno de las regiones.
+    Gradient,
+    /// Imagen - apertura: detalles claros más pequeños que el elemento estructurante.
+    TopHat,
+}
+
+impl MorphOp {
+    pub const ALL: [MorphOp; 6] = [MorphOp::Erode, MorphOp::Dilate, MorphOp::Open, MorphOp::Close, MorphOp::Gradient, MorphOp::TopHat];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MorphOp::Erode => "erode",
+            MorphOp::Dilate => "dilate",
+            MorphOp::Open => "open",
+            MorphOp::Close => "close",
+            MorphOp::Gradient => "gradient",
+            MorphOp::TopHat => "top_hat",
+        }
+    }
+
+    fn code(self) -> i32 {
+        match self {
+            MorphOp::Erode => imgproc::MORPH_ERODE,
+            MorphOp::Dilate => imgproc::MORPH_DILATE,
+            MorphOp::Open => imgproc::MORPH_OPEN,
+            MorphOp::Close => imgproc::MORPH_CLOSE,
+            MorphOp::Gradient => imgproc::MORPH_GRADIENT,
+            MorphOp::TopHat => imgproc::MORPH_TOPHAT,
+        }
+    }
+}
+
+/// Forma del elemento estructurante.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum KernelShape {
+    #[default]
+    Rect,
+    /// Respeta mejor los objetos redondeados.
+    Ellipse,
+    Cross,
+}
+
+impl KernelShape {
+    pub const ALL: [KernelShape; 3] = [KernelShape::Rect, KernelShape::Ellipse, KernelShape::Cross];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            KernelShape::Rect => "rect",
+            KernelShape::Ellipse => "ellipse",
+            KernelShape::Cross => "cross",
+        }
+    }
+
+    fn code(self) -> i32 {
+        match self {
+            KernelShape::Rect => imgproc::MORPH_RECT,
+            KernelShape::Ellipse => imgproc::MORPH_ELLIPSE,
+            KernelShape::Cross => imgproc::MORPH_CROSS,
+        }
+    }
+}
+
+pub struct Morphology;
+
+impl Morphology {
+    /// Aplica `op` con un elemento `shape` de `size` x `size`, repetida `iterations` veces.
+    pub fn apply(src: &Mat, op: MorphOp, shape: KernelShape, size: i32, iterations: i32) -> NixResult<Mat> {
+        Self::check_morphology(size, iterations)?;
+        let kernel = imgproc::get_structuring_element(
+            shape.code(),
+            core::Size::new(size, size),
+            core::Point::new(-1, -1), // Ancla en el centro
+        )?;
+
+        let mut dst = Mat::default();
+        imgproc::morphology_ex(
+            src,
+            &mut dst,
+            op.code(),
+            &kernel,
+            core::Point::new(-1, -1),
+            iterations,
+            core::BORDER_CONSTANT,
+            imgproc::morphology_default_border_value()?,
+        )?;
+        Ok(dst)
+    }
+
+    /// Elemento estructurante de al menos 1x1 y al menos una iteración.
+    pub fn check_morphology(size: i32, iterations: i32) -> NixResult<()> {
+        if size < 1 {
+            return Err(NixError::invalid("morphology", "size", format!("debe ser al menos 1, se recibió {}", size)));
+        }
+        if iterations < 1 {
+            return Err(NixError::invalid("morphology", "iterations", format!("debe ser al menos 1, se recibió {}", iterations)));
+        }
+        Ok(())
+    }
+}
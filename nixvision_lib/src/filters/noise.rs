@@ -1,8 +1,8 @@
 use opencv::{imgproc,
     prelude::*, 
-    core, 
-    Result
+    core
 };
+use crate::error::{NixError, NixResult};
 
 pub struct NoiseReducer;
 
@@ -10,7 +10,8 @@ impl NoiseReducer {
     /// FILTRO 1: Gaussian Blur
     /// Es el estándar para reducir ruido de alta frecuencia (grano).
     /// Tiende a difuminar los bordes del objeto patrón[cite: 52].
-    pub fn gaussian(src: &Mat, kernel_size: i32) -> Result<Mat> {
+    pub fn gaussian(src: &Mat, kernel_size: i32) -> NixResult<Mat> {
+        Self::check_gaussian(kernel_size)?;
         let mut dst = Mat::default(); // Prepara el contenedor para la imagen filtrada.
         imgproc::gaussian_blur_def(
             src, 
@@ -23,7 +24,8 @@ impl NoiseReducer {
 
     /// FILTRO 2: Median Blur
     /// Muy efectivo para el ruido tipo "sal y pimienta" (puntos blancos/negros)[cite: 53].
-    pub fn median(src: &Mat, kernel_size: i32) -> Result<Mat> {
+    pub fn median(src: &Mat, kernel_size: i32) -> NixResult<Mat> {
+        Self::check_median(kernel_size)?;
         let mut dst = Mat::default();
         imgproc::median_blur(src, &mut dst, kernel_size)?;
         Ok(dst)
@@ -32,7 +34,8 @@ impl NoiseReducer {
     /// FILTRO 3: Bilateral Filter (Recomendado para el reporte)
     /// Es un filtro que reduce el ruido pero PRESERVA LOS BORDES.
     /// Ideal para que el Nothing Phone 2a no pierda nitidez en el objeto patrón.
-    pub fn bilateral(src: &Mat, d: i32, sigma_color: f64, sigma_space: f64) -> Result<Mat> {
+    pub fn bilateral(src: &Mat, d: i32, sigma_color: f64, sigma_space: f64) -> NixResult<Mat> {
+        Self::check_bilateral(d, sigma_color, sigma_space)?;
         let mut dst = Mat::default();
         imgproc::bilateral_filter(
             src, 
@@ -44,4 +47,34 @@ impl NoiseReducer {
         )?;
         Ok(dst)
     }
+
+    /// El kernel Gaussiano debe ser impar y positivo (3, 5, 7...).
+    pub fn check_gaussian(kernel_size: i32) -> NixResult<()> {
+        if kernel_size <= 0 || kernel_size % 2 == 0 {
+            return Err(NixError::invalid("gaussian", "kernel_size", format!("debe ser impar y positivo, se recibió {}", kernel_size)));
+        }
+        Ok(())
+    }
+
+    /// La mediana requiere un kernel impar mayor que 1.
+    pub fn check_median(kernel_size: i32) -> NixResult<()> {
+        if kernel_size <= 1 || kernel_size % 2 == 0 {
+            return Err(NixError::invalid("median", "kernel_size", format!("debe ser impar y mayor que 1, se recibió {}", kernel_size)));
+        }
+        Ok(())
+    }
+
+    /// Diámetro positivo (valores > 15 son muy lentos) y sigmas positivos.
+    pub fn check_bilateral(d: i32, sigma_color: f64, sigma_space: f64) -> NixResult<()> {
+        if d <= 0 {
+            return Err(NixError::invalid("bilateral", "d", format!("el diámetro debe ser positivo, se recibió {}", d)));
+        }
+        if sigma_color <= 0.0 {
+            return Err(NixError::invalid("bilateral", "sigma_color", format!("debe ser mayor que 0, se recibió {}", sigma_color)));
+        }
+        if sigma_space <= 0.0 {
+            return Err(NixError::invalid("bilateral", "sigma_space", format!("debe ser mayor que 0, se recibió {}", sigma_space)));
+        }
+        Ok(())
+    }
 }
\ No newline at end of file
@@ -0,0 +1,113 @@
+use opencv::core::Point2f;
+use serde::{Serialize, Deserialize};
+use crate::error::{NixError, NixResult};
+use crate::filters::contour::ContourMetrics;
+
+/// Objeto de referencia de tamaño conocido para calibrar la escala.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reference {
+    /// Objeto alargado (regla, pieza patrón): se usa el lado largo del rectángulo mínimo.
+    Length,
+    /// Marcador cuadrado: lado = √área.
+    Square,
+    /// Marcador circular: diámetro equivalente.
+    Circle,
+}
+
+impl Reference {
+    pub const ALL: [Reference; 3] = [Reference::Length, Reference::Square, Reference::Circle];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Reference::Length => "length",
+            Reference::Square => "square",
+            Reference::Circle => "circle",
+        }
+    }
+
+    /// Tamaño de la referencia en píxeles.
+    fn pixels(self, metrics: &ContourMetrics) -> f64 {
+        match self {
+            Reference::Length => metrics.min_rect.size.width.max(metrics.min_rect.size.height) as f64,
+            Reference::Square => metrics.area.sqrt(),
+            Reference::Circle => metrics.equivalent_diameter,
+        }
+    }
+}
+
+/// Escala física de una cámara en milímetros por píxel (se asumen píxeles cuadrados y
+/// el objeto en el plano de calibración).
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Scale {
+    pub mm_per_pixel: f64,
+}
+
+/// Medidas de un contorno en unidades reales.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct PhysicalMetrics {
+    pub area_mm2: f64,
+    pub net_area_mm2: f64,
+    pub perimeter_mm: f64,
+    /// Ancho y alto del bounding box.
+    pub width_mm: f64,
+    pub height_mm: f64,
+    /// Lados largo y corto del rectángulo rotado mínimo (medidas de la pieza, sin importar su giro).
+    pub length_mm: f64,
+    pub breadth_mm: f64,
+    pub equivalent_diameter_mm: f64,
+}
+
+impl Scale {
+    pub fn new(mm_per_pixel: f64) -> NixResult<Self> {
+        if !mm_per_pixel.is_finite() || mm_per_pixel <= 0.0 {
+            return Err(NixError::invalid("scale", "mm_per_pixel", format!("debe ser mayor que 0, se recibió {}", mm_per_pixel)));
+        }
+        Ok(Self { mm_per_pixel })
+    }
+
+    /// Dos puntos de la imagen separados `distance_mm` en la realidad.
+    pub fn from_points(a: Point2f, b: Point2f, distance_mm: f64) -> NixResult<Self> {
+        let pixels = ((a.x - b.x) as f64).hypot((a.y - b.y) as f64);
+        Self::from_pixels("points", pixels, distance_mm)
+    }
+
+    /// Objeto de referencia detectado cuyo tamaño real (`size_mm`) se conoce.
+    pub fn from_reference(metrics: &ContourMetrics, reference: Reference, size_mm: f64) -> NixResult<Self> {
+        Self::from_pixels(reference.name(), reference.pixels(metrics), size_mm)
+    }
+
+    fn from_pixels(param: &'static str, pixels: f64, size_mm: f64) -> NixResult<Self> {
+        if size_mm.is_nan() || size_mm <= 0.0 {
+            return Err(NixError::invalid("scale", param, format!("la medida real debe ser mayor que 0, se recibió {}", size_mm)));
+        }
+        if pixels.is_nan() || pixels <= 0.0 {
+            return Err(NixError::invalid("scale", param, "la referencia mide 0 px"));
+        }
+        Self::new(size_mm / pixels)
+    }
+
+    /// Longitud en mm de `pixels` píxeles.
+    pub fn length(&self, pixels: f64) -> f64 {
+        pixels * self.mm_per_pixel
+    }
+
+    /// Área en mm² de `pixels` px².
+    pub fn area(&self, pixels: f64) -> f64 {
+        pixels * self.mm_per_pixel * self.mm_per_pixel
+    }
+
+    pub fn measure(&self, metrics: &ContourMetrics) -> PhysicalMetrics {
+        let side_a = metrics.min_rect.size.width as f64;
+        let side_b = metrics.min_rect.size.height as f64;
+        PhysicalMetrics {
+            area_mm2: self.area(metrics.area),
+            net_area_mm2: self.area(metrics.net_area),
+            perimeter_mm: self.length(metrics.perimeter),
+            width_mm: self.length(metrics.width as f64),
+            height_mm: self.length(metrics.height as f64),
+            length_mm: self.length(side_a.max(side_b)),
+            breadth_mm: self.length(side_a.min(side_b)),
+            equivalent_diameter_mm: self.length(metrics.equivalent_diameter),
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use opencv::{imgproc, prelude::*};
+use crate::error::{NixError, NixResult};
+
+/// Valor asignado a los píxeles que superan el umbral (imagen binaria 0/255).
+const MAX_VALUE: f64 = 255.0;
+
+/// Cálculo del umbral local en la umbralización adaptativa.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AdaptiveMethod {
+    /// Media de la vecindad `block_size` x `block_size`.
+    Mean,
+    /// Media ponderada con una ventana gaussiana (menos sensible a bordes cercanos).
+    Gaussian,
+}
+
+pub struct Thresholder; // Segmentación por umbral: produce regiones rellenas para NixContour
+
+impl Thresholder {
+    /// Umbral fijo: blanco si `pixel > thresh` (negro con `invert`). Pensado para imágenes 8U.
+    pub fn fixed(src: &Mat, thresh: f64, invert: bool) -> NixResult<Mat> {
+        Self::check_fixed(thresh)?;
+        let mut dst = Mat::default();
+        imgproc::threshold(src, &mut dst, thresh, MAX_VALUE, Self::kind(invert))?;
+        Ok(dst)
+    }
+
+    /// Otsu: elige el umbral que mejor separa un histograma bimodal (objeto/fondo).
+    /// Devuelve la imagen binaria y el umbral calculado. Requiere 8 bits y 1 canal.
+    pub fn otsu(src: &Mat, invert: bool) -> NixResult<(Mat, f64)> {
+        let mut dst = Mat::default();
+        let thresh = imgproc::threshold(src, &mut dst, 0.0, MAX_VALUE, Self::kind(invert) | imgproc::THRESH_OTSU)?;
+        Ok((dst, thresh))
+    }
+
+    /// Triángulo: adecuado para histogramas con un pico dominante (objeto pequeño sobre fondo).
+    /// Devuelve la imagen binaria y el umbral calculado. Requiere 8 bits y 1 canal.
+    pub fn triangle(src: &Mat, invert: bool) -> NixResult<(Mat, f64)> {
+        let mut dst = Mat::default();
+        let thresh = imgproc::threshold(src, &mut dst, 0.0, MAX_VALUE, Self::kind(invert) | imgproc::THRESH_TRIANGLE)?;
+        Ok((dst, thresh))
+    }
+
+    /// Umbral local: cada píxel se compara con su vecindad menos `c`.
+    /// Útil con iluminación no uniforme, donde ningún umbral global sirve.
+    pub fn adaptive(src: &Mat, method: AdaptiveMethod, block_size: i32, c: f64, invert: bool) -> NixResult<Mat> {
+        Self::check_adaptive(block_size)?;
+        let adaptive_method = match method {
+            AdaptiveMethod::Mean => imgproc::ADAPTIVE_THRESH_MEAN_C,
+            AdaptiveMethod::Gaussian => imgproc::ADAPTIVE_THRESH_GAUSSIAN_C,
+        };
+        let mut dst = Mat::default();
+        imgproc::adaptive_threshold(src, &mut dst, MAX_VALUE, adaptive_method, Self::kind(invert), block_size, c)?;
+        Ok(dst)
+    }
+
+    /// El umbral fijo debe estar dentro del rango de 8 bits.
+    pub fn check_fixed(thresh: f64) -> NixResult<()> {
+        if !(0.0..=MAX_VALUE).contains(&thresh) {
+            return Err(NixError::invalid("threshold", "thresh", format!("debe estar entre 0 y 255, se recibió {}", thresh)));
+        }
+        Ok(())
+    }
+
+    /// La vecindad debe ser impar y mayor que 1 (3, 5, 7...).
+    pub fn check_adaptive(block_size: i32) -> NixResult<()> {
+        if block_size <= 1 || block_size % 2 == 0 {
+            return Err(NixError::invalid(
+                "adaptive_threshold",
+                "block_size",
+                format!("debe ser impar y mayor que 1, se recibió {}", block_size),
+            ));
+        }
+        Ok(())
+    }
+
+    fn kind(invert: bool) -> i32 {
+        if invert { imgproc::THRESH_BINARY_INV } else { imgproc::THRESH_BINARY }
+    }
+}
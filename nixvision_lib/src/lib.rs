@@ -1 +1,2 @@
+pub mod error;
 pub mod filters;
\ No newline at end of file